@@ -0,0 +1,262 @@
+//! Skyrim merges every mod's translation files into one namespace, so two mods
+//! that define the same key with different text clobber each other. Compare
+//! this mod's translations with the ones installed alongside it.
+
+use std::path::{Path, PathBuf};
+
+use comfy_table::presets::UTF8_BORDERS_ONLY;
+use comfy_table::*;
+use eyre::{Report, Result};
+use owo_colors::OwoColorize;
+use walkdir::WalkDir;
+
+use crate::{split_translation_filename, Args, ModDirectory, Translation};
+
+/// A key this mod defines that another mod defines differently.
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub language: String,
+    pub key: String,
+    /// The other mod's translation file, relative to the searched directory.
+    pub other: String,
+    /// This mod's value for the key.
+    pub ours: String,
+    /// The other mod's value for the key.
+    pub theirs: String,
+}
+
+/// Folders of game assets, which never hold translation files and can be huge.
+const ASSET_DIRS: [&str; 5] = ["meshes", "textures", "sound", "music", "seq"];
+
+/// Find the translation files in a game Data folder or a mod manager's mods
+/// folder: every `.txt` in an `Interface/Translations` directory, however deep
+/// a mod keeps its files.
+pub fn installed_translation_files(root: &Path) -> Vec<PathBuf> {
+    let is_named = |path: Option<&Path>, name: &str| {
+        path.and_then(|xs| xs.file_name())
+            .map(|xs| xs.to_string_lossy().eq_ignore_ascii_case(name))
+            .unwrap_or(false)
+    };
+    let mut files: Vec<PathBuf> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || !ASSET_DIRS
+                    .iter()
+                    .any(|xs| e.file_name().to_string_lossy().eq_ignore_ascii_case(xs))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .filter(|path| {
+            let parent = path.parent();
+            path.extension()
+                .map(|xs| xs.eq_ignore_ascii_case("txt"))
+                .unwrap_or(false)
+                && is_named(parent, "translations")
+                && is_named(parent.and_then(|xs| xs.parent()), "interface")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Compare this mod's translation files with every other mod's files for the
+/// same language under the given directory. Files named for one of this mod's
+/// plugins are this mod's own, installed, and are skipped.
+pub fn find_collisions(moddir: &mut ModDirectory, root: &Path) -> Result<Vec<Collision>> {
+    let mut ours = moddir.translation_files()?;
+    let own_plugins: Vec<String> = moddir
+        .translation_file_paths()?
+        .iter()
+        .filter_map(|xs| split_translation_filename(xs))
+        .map(|(plugin, _language)| plugin.to_lowercase())
+        .collect();
+
+    let mut collisions = Vec::new();
+    for fpath in installed_translation_files(root) {
+        let Some((plugin, language)) = split_translation_filename(&fpath) else {
+            continue;
+        };
+        if own_plugins.contains(&plugin.to_lowercase()) {
+            continue;
+        }
+        let Some(trfile) = ours.get_mut(&language) else {
+            continue;
+        };
+        let mine = trfile.translations()?;
+
+        let other = fpath
+            .strip_prefix(root)
+            .unwrap_or(&fpath)
+            .display()
+            .to_string();
+        let mut theirs = Translation::new(fpath.clone(), language.as_str());
+        let theirs = match theirs.translations() {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("Skipping {}: {e:#}", other.bold());
+                continue;
+            }
+        };
+
+        let mut found: Vec<Collision> = mine
+            .iter()
+            .filter_map(|(key, value)| {
+                let their_value = theirs.get(key)?;
+                if their_value == value {
+                    return None;
+                }
+                Some(Collision {
+                    language: language.clone(),
+                    key: key.clone(),
+                    other: other.clone(),
+                    ours: value.clone(),
+                    theirs: their_value.clone(),
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.key.cmp(&b.key));
+        collisions.extend(found);
+    }
+
+    collisions.sort_by(|a, b| a.language.cmp(&b.language).then(a.key.cmp(&b.key)));
+    Ok(collisions)
+}
+
+/// Report keys this mod shares with other installed mods but gives different text.
+pub fn collisions(args: &Args, root: &Path) -> Result<bool, Report> {
+    if !root.is_dir() {
+        return Err(eyre::eyre!("{} is not a directory.", root.display()));
+    }
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let collisions = find_collisions(&mut moddir, root)?;
+    if collisions.is_empty() {
+        log::info!("No other mod under {} redefines this mod's keys.", root.display());
+        return Ok(true);
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_BORDERS_ONLY)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["language", "key", "also defined in", "ours", "theirs"]);
+    for collision in collisions.iter() {
+        table.add_row(vec![
+            Cell::new(collision.language.as_str())
+                .fg(Color::Blue)
+                .add_attribute(Attribute::Bold),
+            Cell::new(collision.key.as_str()).add_attribute(Attribute::Bold),
+            Cell::new(collision.other.as_str()),
+            Cell::new(collision.ours.as_str()),
+            Cell::new(collision.theirs.as_str()).fg(Color::Red),
+        ]);
+    }
+    log::warn!("{table}");
+    log::warn!(
+        "{} keys collide with other mods; only one mod's text can show in game.",
+        collisions.len().bold()
+    );
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    fn installed(root: &Path, relative: &str, lines: &[(&str, &str)]) {
+        let fpath = root.join(relative);
+        std::fs::create_dir_all(fpath.parent().unwrap()).unwrap();
+        let mut trfile = Translation::new(fpath, "english");
+        trfile.set_lines(
+            lines
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        trfile.write().unwrap();
+    }
+
+    /// A mods folder with this mod, two mods that define its title differently,
+    /// one nested a level deeper, and one that agrees with it.
+    fn mods_folder() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let title = "$MyMod_Title";
+        installed(
+            root.path(),
+            "MyMod/Interface/Translations/MyMod_english.txt",
+            &[(title, "Old title")],
+        );
+        installed(
+            root.path(),
+            "Other/Interface/Translations/Other_english.txt",
+            &[(title, "Other title"), ("$Other_Name", "Other")],
+        );
+        installed(
+            root.path(),
+            "Nested/Data/Interface/Translations/Nested_english.txt",
+            &[(title, "Nested title")],
+        );
+        installed(
+            root.path(),
+            "Agrees/interface/translations/Agrees_english.txt",
+            &[(title, "My title")],
+        );
+        installed(
+            root.path(),
+            "Assets/textures/Interface/Translations/Assets_english.txt",
+            &[(title, "Not a translation file")],
+        );
+        root
+    }
+
+    #[test]
+    fn finds_translation_files_at_any_depth() {
+        let root = mods_folder();
+        let found: Vec<String> = installed_translation_files(root.path())
+            .iter()
+            .map(|xs| xs.strip_prefix(root.path()).unwrap().display().to_string())
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "Agrees/interface/translations/Agrees_english.txt",
+                "MyMod/Interface/Translations/MyMod_english.txt",
+                "Nested/Data/Interface/Translations/Nested_english.txt",
+                "Other/Interface/Translations/Other_english.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_keys_other_mods_define_differently() {
+        let root = mods_folder();
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .translation("MyMod_english.txt", &["$MyMod_Title\tMy title"]);
+        let collisions = find_collisions(&mut fixture.moddir(), root.path()).unwrap();
+
+        let found: Vec<(&str, &str, &str)> = collisions
+            .iter()
+            .map(|xs| (xs.key.as_str(), xs.other.as_str(), xs.theirs.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "$MyMod_Title",
+                    "Nested/Data/Interface/Translations/Nested_english.txt",
+                    "Nested title"
+                ),
+                (
+                    "$MyMod_Title",
+                    "Other/Interface/Translations/Other_english.txt",
+                    "Other title"
+                ),
+            ]
+        );
+        assert!(collisions.iter().all(|xs| xs.ours == "My title"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Per-mod project configuration, read from `mcm-meta-helper.toml` in the
+//! mod's root directory. Every setting is optional; a mod without a config
+//! file gets the defaults.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::SUPPRESSIONS_FILE_NAME;
+
+/// The languages Skyrim ships translations for, and so the languages a mod
+/// is expected to provide translation files for unless configured otherwise.
+pub const SKYRIM_LANGUAGES: [&str; 9] = [
+    "english", "french", "german", "italian", "spanish", "polish", "russian", "japanese",
+    "chinese",
+];
+
+/// The name of the config file we look for in the mod root.
+pub const CONFIG_FILE_NAME: &str = "mcm-meta-helper.toml";
+
+/// How to report a kind of problem.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Don't report it.
+    Off,
+    /// Report it without failing the check.
+    #[default]
+    Warning,
+    /// Report it and fail the check.
+    Error,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// The data directory, relative to the mod root. Discovered if not set.
+    pub data_dir: Option<PathBuf>,
+    /// The language other translations are copied from and compared against.
+    pub source_language: String,
+    /// Globs for paths, relative to the mod root, that should never be searched.
+    pub ignore: Vec<String>,
+    /// Globs for additional json files, relative to the mod root, to scan for
+    /// requested translation keys.
+    pub extra_sources: Vec<String>,
+    /// Keys that are intentionally provided without being requested anywhere.
+    pub allowed_unused: Vec<String>,
+    /// Languages that must have a translation file. Defaults to every language
+    /// Skyrim supports; set this to an empty list to turn the check off.
+    pub required_languages: Vec<String>,
+    /// The suppressions file, relative to the mod root.
+    pub suppressions: PathBuf,
+    /// A prefix every key must start with, like `$MyMod_`. `{modName}` is
+    /// replaced with the `modName` from config.json.
+    pub key_prefix: Option<String>,
+    /// A regular expression every key must match.
+    pub key_pattern: Option<String>,
+    /// Whether to use the bundled list of SkyUI's own keys when `skyui_path`
+    /// isn't set.
+    pub bundled_skyui_keys: bool,
+    /// A SkyUI install to read SkyUI's own keys from instead of the bundled
+    /// list: its mod folder, its data folder, or its english translation file.
+    pub skyui_path: Option<PathBuf>,
+    /// Translation files from frameworks like SkyUI or MCM Helper whose keys
+    /// mods may use without providing, relative to the mod root.
+    pub builtin_key_files: Vec<PathBuf>,
+    /// More keys that mods may use without providing.
+    pub builtin_keys: Vec<String>,
+    /// How to report translation files that redefine built-in keys, which
+    /// changes the text for every mod that uses them.
+    pub builtin_overrides: Severity,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            source_language: "english".to_string(),
+            ignore: Vec::new(),
+            extra_sources: Vec::new(),
+            allowed_unused: Vec::new(),
+            required_languages: SKYRIM_LANGUAGES.iter().map(|xs| xs.to_string()).collect(),
+            suppressions: PathBuf::from(SUPPRESSIONS_FILE_NAME),
+            key_prefix: None,
+            key_pattern: None,
+            bundled_skyui_keys: true,
+            skyui_path: None,
+            builtin_key_files: Vec::new(),
+            builtin_keys: Vec::new(),
+            builtin_overrides: Severity::default(),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Read the config file from the given mod directory if it exists, or
+    /// fall back to the defaults if it doesn't.
+    pub fn load(modpath: &Path) -> Result<Self> {
+        let fpath = modpath.join(CONFIG_FILE_NAME);
+        if !fpath.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(&fpath)
+            .context(format!("reading the project config {}", fpath.display()))?;
+        let config: ProjectConfig = toml::from_str(contents.as_str())
+            .context(format!("parsing the project config {}", fpath.display()))?;
+        log::debug!("Read project configuration from {}", fpath.display());
+        Ok(config)
+    }
+
+    /// Compile the ignore globs into a matcher.
+    pub fn ignore_set(&self) -> Result<GlobSet> {
+        build_globset(&self.ignore)
+    }
+
+    /// Compile the extra key source globs into a matcher.
+    pub fn extra_sources_set(&self) -> Result<GlobSet> {
+        build_globset(&self.extra_sources)
+    }
+
+    /// Compile the key naming pattern, if there is one.
+    pub fn key_regex(&self) -> Result<Option<Regex>> {
+        let Some(ref pattern) = self.key_pattern else {
+            return Ok(None);
+        };
+        let regex =
+            Regex::new(pattern).context(format!("parsing the key pattern '{pattern}'"))?;
+        Ok(Some(regex))
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).context(format!("parsing the glob pattern '{pattern}'"))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str) -> Result<ProjectConfig> {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), contents).unwrap();
+        ProjectConfig::load(dir.path())
+    }
+
+    #[test]
+    fn load_gives_the_defaults_without_a_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ProjectConfig::load(dir.path()).unwrap();
+        assert_eq!(config.source_language, "english");
+        assert_eq!(config.required_languages.len(), SKYRIM_LANGUAGES.len());
+        assert!(config.bundled_skyui_keys);
+        assert_eq!(config.builtin_overrides, Severity::Warning);
+    }
+
+    #[test]
+    fn load_reads_settings_and_keeps_defaults_for_the_rest() {
+        let config = load(
+            r#"
+source_language = "german"
+ignore = ["old/**", "*.bak"]
+required_languages = []
+builtin_overrides = "error"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.source_language, "german");
+        assert_eq!(config.ignore, ["old/**", "*.bak"]);
+        assert!(config.required_languages.is_empty());
+        assert_eq!(config.builtin_overrides, Severity::Error);
+        assert_eq!(config.suppressions, PathBuf::from(SUPPRESSIONS_FILE_NAME));
+    }
+
+    #[test]
+    fn load_rejects_unknown_settings() {
+        assert!(load("source_langauge = \"german\"\n").is_err());
+        assert!(load("builtin_overrides = \"loud\"\n").is_err());
+    }
+
+    #[test]
+    fn ignore_set_matches_paths_relative_to_the_mod_root() {
+        let config = load("ignore = [\"old/**\", \"*.bak\"]\n").unwrap();
+        let ignore = config.ignore_set().unwrap();
+        assert!(ignore.is_match("old/Interface/Translations/MyMod_english.txt"));
+        assert!(ignore.is_match("config.bak"));
+        assert!(!ignore.is_match("data/Interface/Translations/MyMod_english.txt"));
+    }
+
+    #[test]
+    fn ignore_set_rejects_a_bad_glob() {
+        let config = load("ignore = [\"old/[\"]\n").unwrap();
+        assert!(config.ignore_set().is_err());
+    }
+}
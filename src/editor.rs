@@ -0,0 +1,682 @@
+//! A terminal editor for translations, for translators who would rather not
+//! edit UTF-16 files full of tabs. Keys are rows and languages are columns,
+//! with the source language pinned next to the keys.
+
+use std::collections::HashSet;
+use std::io::{IsTerminal, Stdout};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use eyre::{Report, Result};
+use owo_colors::OwoColorize;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::{is_stub, Args, ModDirectory, Translation};
+
+/// The narrowest a language column gets before we scroll sideways instead.
+const MIN_COLUMN_WIDTH: u16 = 24;
+
+/// What a translation cell holds, as far as a translator is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellState {
+    Missing,
+    /// The stub value written by `update`.
+    Stub,
+    /// The same text as the source language, probably from `copy`.
+    SameAsSource,
+    Translated,
+}
+
+impl CellState {
+    fn style(&self) -> Style {
+        match self {
+            CellState::Missing => Style::default().fg(Color::Red),
+            CellState::Stub => Style::default().fg(Color::Yellow),
+            CellState::SameAsSource => Style::default().fg(Color::Magenta),
+            CellState::Translated => Style::default(),
+        }
+    }
+}
+
+enum Mode {
+    Browsing,
+    /// Editing the selected cell; the text so far and the cursor position in it.
+    Editing {
+        text: Vec<char>,
+        cursor: usize,
+    },
+    /// Asked to quit with unsaved changes.
+    ConfirmingQuit,
+}
+
+struct Editor {
+    name: String,
+    /// Languages with a translation file, source language first.
+    languages: Vec<String>,
+    /// The source language's column, if it has a translation file.
+    source: Option<usize>,
+    files: Vec<Translation>,
+    keys: Vec<String>,
+    /// The value of every key in every language, by row and then column.
+    values: Vec<Vec<Option<String>>>,
+    /// Cells changed since the last save, as (row, column).
+    changed: HashSet<(usize, usize)>,
+    /// Key orderings new translations are inserted by, as `update` does.
+    orders: [Vec<String>; 2],
+    row: usize,
+    column: usize,
+    /// The first row on screen.
+    top: usize,
+    /// The first language column on screen after the pinned source language.
+    left: usize,
+    mode: Mode,
+    status: String,
+}
+
+/// Edit this mod's translations in the terminal until the translator quits.
+pub fn edit(args: &Args, languages: &[String]) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let mut editor = Editor::new(&mut moddir, languages)?;
+    if !std::io::stdout().is_terminal() {
+        return Err(eyre::eyre!(
+            "The translation editor needs a terminal to run in."
+        ));
+    }
+
+    let guard = TerminalGuard::new()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    let result = editor.run(&mut terminal);
+    drop(guard);
+    result?;
+
+    match editor.changed.len() {
+        0 => log::info!("All changes saved."),
+        1 => log::warn!("Quit without saving {} changed translation.", "1".bold()),
+        count => log::warn!("Quit without saving {} changed translations.", count.bold()),
+    }
+    Ok(true)
+}
+
+/// Puts the terminal into raw mode on the alternate screen, and back again
+/// when dropped, even if the editor fails.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            std::io::stdout(),
+            LeaveAlternateScreen,
+            crossterm::cursor::Show
+        );
+    }
+}
+
+impl Editor {
+    fn new(moddir: &mut ModDirectory, wanted: &[String]) -> Result<Self> {
+        let source_language = moddir.config().source_language.clone();
+        let mut files: Vec<Translation> = moddir.translation_files()?.into_values().collect();
+        files.sort_by_key(|xs| (xs.language() != source_language, xs.language().to_string()));
+        if files.is_empty() {
+            return Err(eyre::eyre!("This mod has no translation files to edit."));
+        }
+        let available: Vec<String> = files.iter().map(|xs| xs.language().to_string()).collect();
+        if let Some(unknown) = wanted.iter().find(|xs| !available.contains(xs)) {
+            return Err(eyre::eyre!(
+                "There is no {unknown} translation file; choose from {}.",
+                available.join(", ")
+            ));
+        }
+        if !wanted.is_empty() {
+            files.retain(|xs| {
+                xs.language() == source_language || wanted.iter().any(|w| w == xs.language())
+            });
+        }
+
+        // Rows follow the source language file, then any required keys it lacks
+        // in the order the json files request them.
+        let config_order = moddir.requested_keys_in_order()?;
+        let source_order = match files.first_mut() {
+            Some(source) if source.language() == source_language => {
+                source.translations()?;
+                source.ordered_keys()
+            }
+            _ => Vec::new(),
+        };
+        let required = moddir.required_translations()?;
+        let mut keys: Vec<String> = Vec::new();
+        for key in source_order.iter().chain(config_order.iter()) {
+            if (required.contains(key) || source_order.contains(key)) && !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+
+        let mut columns = Vec::new();
+        for trfile in files.iter_mut() {
+            columns.push(trfile.translations()?);
+        }
+        let values = keys
+            .iter()
+            .map(|key| columns.iter().map(|xs| xs.get(key).cloned()).collect())
+            .collect();
+
+        let languages: Vec<String> = files.iter().map(|xs| xs.language().to_string()).collect();
+        Ok(Self {
+            name: moddir.name().to_string(),
+            source: languages.iter().position(|xs| *xs == source_language),
+            languages,
+            files,
+            keys,
+            values,
+            changed: HashSet::new(),
+            orders: [source_order, config_order],
+            row: 0,
+            column: 0,
+            top: 0,
+            left: 1,
+            mode: Mode::Browsing,
+            status: String::new(),
+        })
+    }
+
+    fn state(&self, row: usize, column: usize) -> CellState {
+        let Some(ref value) = self.values[row][column] else {
+            return CellState::Missing;
+        };
+        if is_stub(&self.keys[row], value) {
+            CellState::Stub
+        } else if self.source.is_some_and(|source| {
+            source != column && self.values[row][source].as_ref() == Some(value)
+        }) {
+            CellState::SameAsSource
+        } else {
+            CellState::Translated
+        }
+    }
+
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Press && self.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Act on a key press in whatever mode the editor is in. Returns true to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match self.mode {
+            Mode::Browsing => self.browse(key),
+            Mode::Editing { .. } => {
+                self.type_key(key);
+                false
+            }
+            Mode::ConfirmingQuit => match key.code {
+                KeyCode::Char('q') => true,
+                KeyCode::Char('s') => {
+                    self.mode = Mode::Browsing;
+                    self.save();
+                    self.changed.is_empty()
+                }
+                _ => {
+                    self.mode = Mode::Browsing;
+                    self.status.clear();
+                    false
+                }
+            },
+        }
+    }
+
+    /// Handle a key while moving around the table. Returns true to quit.
+    fn browse(&mut self, key: KeyEvent) -> bool {
+        let last_row = self.keys.len().saturating_sub(1);
+        let last_column = self.languages.len() - 1;
+        match key.code {
+            KeyCode::Char('s') => self.save(),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if self.changed.is_empty() {
+                    return true;
+                }
+                self.status = format!(
+                    "{} unsaved changes: press s to save and quit, q to quit without saving, or anything else to keep editing.",
+                    self.changed.len()
+                );
+                self.mode = Mode::ConfirmingQuit;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.row = (self.row + 1).min(last_row),
+            KeyCode::Left | KeyCode::Char('h') => self.column = self.column.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.column = (self.column + 1).min(last_column),
+            KeyCode::PageUp => self.row = self.row.saturating_sub(20),
+            KeyCode::PageDown => self.row = (self.row + 20).min(last_row),
+            KeyCode::Home | KeyCode::Char('g') => self.row = 0,
+            KeyCode::End | KeyCode::Char('G') => self.row = last_row,
+            KeyCode::Char('n') => self.next_problem(),
+            KeyCode::Enter | KeyCode::Char('e') if !self.keys.is_empty() => {
+                let text: Vec<char> = self.values[self.row][self.column]
+                    .clone()
+                    .unwrap_or_default()
+                    .chars()
+                    .collect();
+                self.mode = Mode::Editing {
+                    cursor: text.len(),
+                    text,
+                };
+                self.status.clear();
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle a key while editing a cell.
+    fn type_key(&mut self, key: KeyEvent) {
+        let Mode::Editing {
+            ref mut text,
+            ref mut cursor,
+        } = self.mode
+        else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Browsing;
+            }
+            KeyCode::Enter => {
+                let value = String::from_iter(text.iter()).trim().to_string();
+                if value.is_empty() {
+                    self.status = "A translation can't be empty; press esc to leave it as it was."
+                        .to_string();
+                    return;
+                }
+                let cell = &mut self.values[self.row][self.column];
+                if cell.as_ref() != Some(&value) {
+                    *cell = Some(value);
+                    self.changed.insert((self.row, self.column));
+                }
+                self.mode = Mode::Browsing;
+            }
+            KeyCode::Left => *cursor = cursor.saturating_sub(1),
+            KeyCode::Right => *cursor = (*cursor + 1).min(text.len()),
+            KeyCode::Home => *cursor = 0,
+            KeyCode::End => *cursor = text.len(),
+            KeyCode::Backspace if *cursor > 0 => {
+                *cursor -= 1;
+                text.remove(*cursor);
+            }
+            KeyCode::Delete if *cursor < text.len() => {
+                text.remove(*cursor);
+            }
+            // Tabs separate keys from values, so a value can't contain one.
+            KeyCode::Char(c) if c != '\t' => {
+                text.insert(*cursor, c);
+                *cursor += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Move to the next missing, stubbed, or untranslated cell, wrapping around.
+    fn next_problem(&mut self) {
+        let width = self.languages.len();
+        let count = self.keys.len() * width;
+        let here = self.row * width + self.column;
+        for step in 1..=count {
+            let idx = (here + step) % count;
+            let (row, column) = (idx / width, idx % width);
+            if self.state(row, column) != CellState::Translated {
+                self.row = row;
+                self.column = column;
+                return;
+            }
+        }
+        self.status = "Every cell is translated.".to_string();
+    }
+
+    /// Write every changed translation back to its file.
+    fn save(&mut self) {
+        if self.changed.is_empty() {
+            self.status = "Nothing to save.".to_string();
+            return;
+        }
+        let orders = [self.orders[0].as_slice(), self.orders[1].as_slice()];
+        let mut saved = 0;
+        for column in 0..self.languages.len() {
+            let mut rows: Vec<usize> = self
+                .changed
+                .iter()
+                .filter(|(_row, xs)| *xs == column)
+                .map(|(row, _column)| *row)
+                .collect();
+            if rows.is_empty() {
+                continue;
+            }
+            rows.sort();
+            let trfile = &mut self.files[column];
+            for row in rows.iter() {
+                let key = &self.keys[*row];
+                let value = self.values[*row][column].clone().unwrap_or_default();
+                if !trfile.set_value(key, &value) {
+                    trfile.insert_in_order(key.clone(), value, &orders);
+                }
+            }
+            if let Err(e) = trfile.write() {
+                self.status = format!("{e:#}");
+                return;
+            }
+            for row in rows {
+                self.changed.remove(&(row, column));
+            }
+            saved += 1;
+        }
+        self.status = if saved == 1 {
+            "Saved 1 translation file.".to_string()
+        } else {
+            format!("Saved {saved} translation files.")
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(5),
+                Constraint::Length(4),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        let counted = |state: CellState| {
+            (0..self.keys.len())
+                .flat_map(|row| (0..self.languages.len()).map(move |column| (row, column)))
+                .filter(|(row, column)| self.state(*row, *column) == state)
+                .count()
+        };
+        let title = Line::from(vec![
+            Span::styled(
+                self.name.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                ": {} keys in {} languages  ",
+                self.keys.len(),
+                self.languages.len()
+            )),
+            Span::styled(
+                format!("{} missing  ", counted(CellState::Missing)),
+                CellState::Missing.style(),
+            ),
+            Span::styled(
+                format!("{} stubs  ", counted(CellState::Stub)),
+                CellState::Stub.style(),
+            ),
+            Span::styled(
+                format!("{} same as source", counted(CellState::SameAsSource)),
+                CellState::SameAsSource.style(),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(title), areas[0]);
+
+        self.draw_table(frame, areas[1]);
+        self.draw_cell(frame, areas[2]);
+
+        let help = match self.mode {
+            Mode::Browsing if self.status.is_empty() => {
+                "arrows move  enter edit  n next problem  s save  q quit".to_string()
+            }
+            Mode::Editing { .. } if self.status.is_empty() => "enter keep  esc cancel".to_string(),
+            _ => self.status.clone(),
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().add_modifier(Modifier::DIM)),
+            areas[3],
+        );
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        let key_width = self
+            .keys
+            .iter()
+            .map(|xs| xs.chars().count())
+            .max()
+            .unwrap_or(3)
+            .clamp(3, 40) as u16;
+
+        // Keep the selected cell on screen.
+        let height = area.height.saturating_sub(3) as usize;
+        if self.row < self.top {
+            self.top = self.row;
+        } else if height > 0 && self.row >= self.top + height {
+            self.top = self.row + 1 - height;
+        }
+        let room = area.width.saturating_sub(key_width + 2);
+        let fits = usize::max(1, (room / MIN_COLUMN_WIDTH) as usize);
+        let scrolling = fits.saturating_sub(1).max(1);
+        if self.column > 0 {
+            if self.column < self.left {
+                self.left = self.column;
+            } else if self.column >= self.left + scrolling {
+                self.left = self.column + 1 - scrolling;
+            }
+        }
+        let mut columns = vec![0];
+        columns.extend((self.left..self.languages.len()).take(fits - 1));
+
+        let header = Row::new(std::iter::once(Cell::from("key")).chain(columns.iter().map(
+            |column| {
+                let language = &self.languages[*column];
+                if self.changed.iter().any(|(_row, xs)| xs == column) {
+                    Cell::from(format!("{language} *"))
+                } else {
+                    Cell::from(language.as_str())
+                }
+            },
+        )))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = (self.top..self.keys.len())
+            .take(height)
+            .map(|row| {
+                let key_style = if row == self.row {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let cells = columns.iter().map(|column| {
+                    let state = self.state(row, *column);
+                    let text = match self.values[row][*column] {
+                        Some(ref value) => value.clone(),
+                        None => "(missing)".to_string(),
+                    };
+                    let mut style = state.style();
+                    if (row, *column) == (self.row, self.column) {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Cell::from(text).style(style)
+                });
+                Row::new(
+                    std::iter::once(Cell::from(self.keys[row].as_str()).style(key_style))
+                        .chain(cells),
+                )
+            })
+            .collect();
+
+        let mut widths = vec![Constraint::Length(key_width)];
+        widths.extend(columns.iter().map(|_| Constraint::Min(MIN_COLUMN_WIDTH)));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(table, area);
+    }
+
+    /// Show the whole selected value, or the text being typed.
+    fn draw_cell(&self, frame: &mut Frame, area: Rect) {
+        if self.keys.is_empty() {
+            return;
+        }
+        let title = format!("{} in {}", self.keys[self.row], self.languages[self.column]);
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(area);
+        match self.mode {
+            Mode::Editing { ref text, cursor } => {
+                // Scroll sideways so the cursor stays in view.
+                let width = inner.width.saturating_sub(1) as usize;
+                let mut start = 0;
+                while Span::raw(String::from_iter(&text[start..cursor])).width() > width {
+                    start += 1;
+                }
+                let shown = String::from_iter(&text[start..]);
+                let before = Span::raw(String::from_iter(&text[start..cursor])).width() as u16;
+                frame.render_widget(Paragraph::new(shown).block(block), area);
+                frame.set_cursor(inner.x + before, inner.y);
+            }
+            _ => {
+                let text = match self.values[self.row][self.column] {
+                    Some(ref value) => Span::raw(value.clone()),
+                    None => Span::styled("(missing)", CellState::Missing.style()),
+                };
+                frame.render_widget(
+                    Paragraph::new(text).wrap(Wrap { trim: false }).block(block),
+                    area,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    fn press(editor: &mut Editor, keys: &[KeyCode]) {
+        for key in keys {
+            editor.handle_key(KeyEvent::from(*key));
+        }
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    fn fixture() -> ModFixture {
+        ModFixture::new(&["$MyMod_A", "$MyMod_B", "$MyMod_C"])
+            .translation(
+                "MyMod_english.txt",
+                &["$MyMod_A\tApple", "$MyMod_B\tBanana", "$MyMod_C\tCherry"],
+            )
+            .translation("MyMod_german.txt", &["$MyMod_A\tApfel", "$MyMod_C\tCherry"])
+    }
+
+    #[test]
+    fn editing_a_cell_changes_only_that_cell() {
+        let fixture = fixture();
+        let mut editor = Editor::new(&mut fixture.moddir(), &[]).unwrap();
+        assert_eq!(editor.languages, ["english", "german"]);
+
+        press(&mut editor, &[KeyCode::Right, KeyCode::Enter]);
+        press(&mut editor, &[KeyCode::Backspace, KeyCode::Backspace]);
+        type_text(&mut editor, "elmus");
+        press(&mut editor, &[KeyCode::Enter]);
+        assert_eq!(editor.values[0][1].as_deref(), Some("Apfelmus"));
+        assert_eq!(editor.changed, HashSet::from([(0, 1)]));
+
+        // Escape leaves the cell as it was.
+        press(&mut editor, &[KeyCode::Down, KeyCode::Enter]);
+        type_text(&mut editor, "Banane");
+        press(&mut editor, &[KeyCode::Esc]);
+        assert_eq!(editor.values[1][1], None);
+        assert_eq!(editor.changed.len(), 1);
+    }
+
+    #[test]
+    fn saving_writes_changed_values_in_place_and_inserts_new_ones() {
+        let fixture = fixture();
+        let mut editor = Editor::new(&mut fixture.moddir(), &[]).unwrap();
+        press(
+            &mut editor,
+            &[KeyCode::Right, KeyCode::Down, KeyCode::Enter],
+        );
+        type_text(&mut editor, "Banane");
+        press(
+            &mut editor,
+            &[KeyCode::Enter, KeyCode::Down, KeyCode::Enter],
+        );
+        press(
+            &mut editor,
+            &[KeyCode::End, KeyCode::Backspace, KeyCode::Backspace],
+        );
+        type_text(&mut editor, "ze");
+        press(&mut editor, &[KeyCode::Enter, KeyCode::Char('s')]);
+        assert!(editor.changed.is_empty());
+
+        let written = std::fs::read(fixture.translations_dir().join("MyMod_german.txt")).unwrap();
+        let mut expected: Vec<u8> = vec![0xff, 0xfe];
+        for unit in "$MyMod_A\tApfel\r\n$MyMod_B\tBanane\r\n$MyMod_C\tCherze\r\n".encode_utf16() {
+            expected.extend(unit.to_le_bytes());
+        }
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn quitting_with_unsaved_changes_asks_first() {
+        let fixture = fixture();
+        let mut editor = Editor::new(&mut fixture.moddir(), &[]).unwrap();
+        press(&mut editor, &[KeyCode::Right, KeyCode::Enter]);
+        type_text(&mut editor, "!");
+        press(&mut editor, &[KeyCode::Enter]);
+
+        assert!(!editor.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+        assert!(!editor.handle_key(KeyEvent::from(KeyCode::Char('x'))));
+        assert!(!editor.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+        assert!(editor.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn next_problem_visits_untranslated_cells() {
+        let fixture = fixture();
+        let mut editor = Editor::new(&mut fixture.moddir(), &[]).unwrap();
+        assert_eq!(editor.state(0, 1), CellState::Translated);
+        assert_eq!(editor.state(1, 1), CellState::Missing);
+        assert_eq!(editor.state(2, 1), CellState::SameAsSource);
+
+        press(&mut editor, &[KeyCode::Char('n')]);
+        assert_eq!((editor.row, editor.column), (1, 1));
+        press(&mut editor, &[KeyCode::Char('n')]);
+        assert_eq!((editor.row, editor.column), (2, 1));
+        press(&mut editor, &[KeyCode::Char('n')]);
+        assert_eq!((editor.row, editor.column), (1, 1));
+    }
+
+    #[test]
+    fn without_a_source_file_nothing_is_same_as_source() {
+        let fixture = ModFixture::new(&["$MyMod_A"])
+            .translation("MyMod_french.txt", &["$MyMod_A\tPomme"])
+            .translation("MyMod_german.txt", &["$MyMod_A\tPomme"]);
+        let editor = Editor::new(&mut fixture.moddir(), &[]).unwrap();
+        assert_eq!(editor.source, None);
+        assert_eq!(editor.state(0, 1), CellState::Translated);
+    }
+}
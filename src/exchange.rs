@@ -0,0 +1,489 @@
+//! Exporting translations to the formats translators' tools speak, and
+//! importing their work back into the mod's translation files.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use comfy_table::presets::UTF8_BORDERS_ONLY;
+use comfy_table::*;
+use eyre::{Context, Report, Result};
+use owo_colors::OwoColorize;
+
+use crate::{
+    is_stub, read_csv, read_po, read_xliff, split_translation_filename, write_csv_file,
+    write_po_files, write_xliff_files, Args, ModDirectory,
+};
+
+/// A file format for exchanging translations with translators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExchangeFormat {
+    /// A gettext POT template and a PO file per language.
+    Po,
+    /// An XLIFF 2.0 file per language.
+    Xliff,
+    /// One CSV sheet with a column per language.
+    Csv,
+}
+
+/// The BCP 47 codes other tools use for the languages Skyrim supports, and for
+/// the languages the community commonly translates it into.
+const LANGUAGE_CODES: [(&str, &str); 21] = [
+    ("english", "en"),
+    ("french", "fr"),
+    ("german", "de"),
+    ("italian", "it"),
+    ("spanish", "es"),
+    ("polish", "pl"),
+    ("russian", "ru"),
+    ("japanese", "ja"),
+    ("chinese", "zh"),
+    ("czech", "cs"),
+    ("brazilian", "pt-BR"),
+    ("portuguese", "pt"),
+    ("mexican", "es-MX"),
+    ("korean", "ko"),
+    ("turkish", "tr"),
+    ("ukrainian", "uk"),
+    ("hungarian", "hu"),
+    ("dutch", "nl"),
+    ("swedish", "sv"),
+    ("danish", "da"),
+    ("finnish", "fi"),
+];
+
+/// The language code other tools know a Skyrim language by, if we know one.
+pub fn language_code(language: &str) -> Option<&'static str> {
+    LANGUAGE_CODES
+        .iter()
+        .find(|(name, _code)| *name == language)
+        .map(|(_name, code)| *code)
+}
+
+/// The Skyrim language for a language code like `de`, `de_DE`, or `pt-BR`, or
+/// a Skyrim language name. A code with a region we don't list is taken as its
+/// base language.
+pub fn language_for_code(code: &str) -> Option<String> {
+    let code = code.trim().to_lowercase().replace('_', "-");
+    let base = code.split('-').next().unwrap_or_default();
+    LANGUAGE_CODES
+        .iter()
+        .find(|(name, short)| short.to_lowercase() == code || *name == code)
+        .or_else(|| LANGUAGE_CODES.iter().find(|(_name, short)| *short == base))
+        .map(|(name, _short)| name.to_string())
+}
+
+/// One requested key, with everything a translator needs to translate it.
+#[derive(Debug, Clone)]
+pub struct ExchangeEntry {
+    pub key: String,
+    /// Where the mod requests the key, as `path:line`.
+    pub locations: Vec<String>,
+    /// The source language text, if the source language has any.
+    pub source: Option<String>,
+    /// Each target language's translation. Missing translations, stubs, and
+    /// copies of the source text are left out, since nobody has translated them.
+    pub translations: HashMap<String, String>,
+}
+
+/// Every requested key and its translations, ready to export.
+#[derive(Debug, Clone)]
+pub struct ExchangeSet {
+    /// The name exported files are named after.
+    pub name: String,
+    pub source_language: String,
+    /// Languages with a translation file, other than the source language, sorted.
+    pub languages: Vec<String>,
+    /// Entries in the order the json files request their keys.
+    pub entries: Vec<ExchangeEntry>,
+}
+
+impl ExchangeSet {
+    pub fn gather(moddir: &mut ModDirectory) -> Result<Self> {
+        let source_language = moddir.config().source_language.clone();
+        let required: HashSet<String> = HashSet::from_iter(moddir.required_translations()?);
+        let locations = key_locations(moddir)?;
+
+        let mut values: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (language, mut trfile) in moddir.translation_files()? {
+            values.insert(language, trfile.translations()?);
+        }
+        let source_values = values.remove(&source_language).unwrap_or_default();
+        let mut languages: Vec<String> = values.keys().cloned().collect();
+        languages.sort();
+
+        let entries = moddir
+            .requested_keys_in_order()?
+            .into_iter()
+            .filter(|key| required.contains(key))
+            .map(|key| {
+                let source = source_values
+                    .get(&key)
+                    .filter(|value| !is_stub(&key, value))
+                    .cloned();
+                let translations = values
+                    .iter()
+                    .filter_map(|(language, translations)| {
+                        let value = translations.get(&key)?;
+                        if is_stub(&key, value) || source_values.get(&key) == Some(value) {
+                            return None;
+                        }
+                        Some((language.clone(), value.clone()))
+                    })
+                    .collect();
+                ExchangeEntry {
+                    locations: locations.get(&key).cloned().unwrap_or_default(),
+                    key,
+                    source,
+                    translations,
+                }
+            })
+            .collect();
+
+        let name = match moddir.mcm_mod_name()? {
+            Some(name) => name,
+            None => moddir.name().to_string(),
+        };
+        Ok(Self {
+            name,
+            source_language,
+            languages,
+            entries,
+        })
+    }
+}
+
+/// Where each requested key first appears in each json file that requests it,
+/// as `path:line` with the path relative to the mod directory.
+fn key_locations(moddir: &mut ModDirectory) -> Result<HashMap<String, Vec<String>>> {
+    let mut locations: HashMap<String, Vec<String>> = HashMap::new();
+    for (jpath, keys) in moddir.requested_keys_by_file()? {
+        let text =
+            std::fs::read_to_string(&jpath).context(format!("reading {}", jpath.display()))?;
+        let display = moddir.relative_display(&jpath).replace('\\', "/");
+        for key in keys {
+            let quoted = format!("\"{key}");
+            let line = text
+                .lines()
+                .position(|xs| xs.contains(&quoted))
+                .or_else(|| text.lines().position(|xs| xs.contains(&key)));
+            let location = match line {
+                Some(idx) => format!("{display}:{}", idx + 1),
+                None => display.clone(),
+            };
+            let found = locations.entry(key).or_default();
+            if !found.contains(&location) {
+                found.push(location);
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// Translations read from a file a translator sent back, for one language.
+#[derive(Debug, Clone)]
+pub struct ImportedTranslations {
+    pub language: String,
+    /// Keys and their translated values, in the order the file has them.
+    pub values: Vec<(String, String)>,
+}
+
+/// What importing did to one language's translation file.
+#[derive(Debug, Clone, Default)]
+struct ImportOutcome {
+    added: usize,
+    /// Values that replaced a stub or a copy of the source text.
+    filled: usize,
+    /// Values that replaced a different translation, with the old value.
+    changed: Vec<(String, String, String)>,
+    unchanged: usize,
+    /// Keys the mod doesn't request.
+    unknown: Vec<String>,
+    /// Keys whose value can't be written to a translation file.
+    rejected: Vec<String>,
+}
+
+/// Write every requested key and its translations to files in the given format.
+pub fn export(args: &Args, format: ExchangeFormat, output: &Path) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let set = ExchangeSet::gather(&mut moddir)?;
+    std::fs::create_dir_all(output).context(format!(
+        "creating the output directory {}",
+        output.display()
+    ))?;
+
+    let written: Vec<PathBuf> = match format {
+        ExchangeFormat::Po => write_po_files(&set, output)?,
+        ExchangeFormat::Xliff => write_xliff_files(&set, output)?,
+        ExchangeFormat::Csv => write_csv_file(&set, output)?,
+    };
+    log::info!(
+        "Exported {} keys in {} languages:",
+        set.entries.len().bold(),
+        (set.languages.len() + 1).bold()
+    );
+    for fpath in written {
+        log::info!("    {}", fpath.display().blue());
+    }
+    Ok(true)
+}
+
+/// Merge translations from exported files that translators filled in back into
+/// the mod's translation files.
+pub fn import(args: &Args, files: &[PathBuf], dry_run: bool) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let set = ExchangeSet::gather(&mut moddir)?;
+    let known: HashSet<&str> = set.entries.iter().map(|xs| xs.key.as_str()).collect();
+    let source_order: Vec<String> = match moddir.translation_file_for(&set.source_language)? {
+        Some(mut source) => {
+            source.load_translations()?;
+            source.ordered_keys()
+        }
+        None => Vec::new(),
+    };
+    let config_order: Vec<String> = set.entries.iter().map(|xs| xs.key.clone()).collect();
+
+    // Read every file before changing anything, so a bad file leaves the
+    // translation files as they were.
+    let mut pending: Vec<ImportedTranslations> = Vec::new();
+    for fpath in files {
+        let extension = fpath
+            .extension()
+            .map(|xs| xs.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let imported = match extension.as_str() {
+            "po" => read_po(fpath)?,
+            "xlf" | "xliff" => read_xliff(fpath, &set.source_language)?,
+            "csv" => read_csv(fpath, &set.source_language, &set.languages)?,
+            _ => {
+                return Err(eyre::eyre!(
+                    "Don't know how to import {}; expected a .po, .xlf, .xliff, or .csv file.",
+                    fpath.display()
+                ))
+            }
+        };
+        for imported in imported.iter() {
+            let language = imported.language.as_str();
+            if moddir.translation_file_for(language)?.is_none() {
+                return Err(eyre::eyre!(
+                    "There is no {language} translation file to import {} into; run `mcm-meta-helper update --create-missing` first.",
+                    fpath.display()
+                ));
+            }
+        }
+        pending.extend(imported);
+    }
+
+    let mut passed = true;
+    for imported in pending {
+        let language = imported.language.as_str();
+        let Some(mut trfile) = moddir.translation_file_for(language)? else {
+            continue;
+        };
+        let current = trfile.translations()?;
+        let source_values = set
+            .entries
+            .iter()
+            .filter_map(|xs| xs.source.as_ref().map(|source| (xs.key.as_str(), source)))
+            .collect::<HashMap<&str, &String>>();
+
+        let mut outcome = ImportOutcome::default();
+        for (key, value) in imported.values.iter() {
+            if !known.contains(key.as_str()) {
+                outcome.unknown.push(key.clone());
+                continue;
+            }
+            if value.contains(['\t', '\n', '\r']) {
+                outcome.rejected.push(key.clone());
+                continue;
+            }
+            match current.get(key) {
+                Some(old) if old == value => {
+                    outcome.unchanged += 1;
+                    continue;
+                }
+                Some(old) if is_stub(key, old) || source_values.get(key.as_str()) == Some(&old) => {
+                    outcome.filled += 1;
+                }
+                Some(old) => outcome
+                    .changed
+                    .push((key.clone(), old.clone(), value.clone())),
+                None => outcome.added += 1,
+            }
+            if !trfile.set_value(key, value) {
+                trfile.insert_in_order(
+                    key.clone(),
+                    value.clone(),
+                    &[source_order.as_slice(), config_order.as_slice()],
+                );
+            }
+        }
+
+        let touched = outcome.added + outcome.filled + outcome.changed.len();
+        if touched > 0 && !dry_run {
+            trfile.write()?;
+            moddir.forget(&[trfile.path().clone()]);
+        }
+        if !outcome.rejected.is_empty() {
+            passed = false;
+        }
+        report_import(trfile.display(), &outcome, dry_run);
+    }
+    Ok(passed)
+}
+
+fn report_import(display: &str, outcome: &ImportOutcome, dry_run: bool) {
+    let note = if dry_run {
+        " (dry run; nothing written)"
+    } else {
+        ""
+    };
+    log::info!(
+        "{}: {} added, {} filled in, {} changed, {} already up to date{note}",
+        display.bold().blue(),
+        outcome.added,
+        outcome.filled,
+        outcome.changed.len(),
+        outcome.unchanged
+    );
+    if !outcome.changed.is_empty() {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_BORDERS_ONLY)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec!["key", "was", "now"]);
+        for (key, old, new) in outcome.changed.iter() {
+            table.add_row(vec![key.as_str(), old.as_str(), new.as_str()]);
+        }
+        log::info!("Conflicts with existing translations, where the imported value wins:\n{table}");
+    }
+    if !outcome.unknown.is_empty() {
+        log::warn!(
+            "Skipped {} keys this mod doesn't request: {}",
+            outcome.unknown.len(),
+            outcome.unknown.join(", ")
+        );
+    }
+    if !outcome.rejected.is_empty() {
+        log::warn!(
+            "Skipped {} values containing tabs or line breaks, which translation files can't hold: {}",
+            outcome.rejected.len().red(),
+            outcome.rejected.join(", ")
+        );
+    }
+}
+
+/// Work out which language an imported file is for from its name, as the
+/// exported files are named `<ModName>_<language>.<extension>`.
+pub fn language_from_filename(fpath: &Path) -> Option<String> {
+    let (_name, language) = split_translation_filename(fpath)?;
+    language_for_code(&language).or(Some(language))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn language_codes_both_ways() {
+        assert_eq!(language_code("german"), Some("de"));
+        assert_eq!(language_code("brazilian"), Some("pt-BR"));
+        assert_eq!(language_code("elvish"), None);
+
+        assert_eq!(language_for_code("de").as_deref(), Some("german"));
+        assert_eq!(language_for_code("de_DE").as_deref(), Some("german"));
+        assert_eq!(language_for_code("pt-BR").as_deref(), Some("brazilian"));
+        assert_eq!(language_for_code("pt_br").as_deref(), Some("brazilian"));
+        assert_eq!(language_for_code("pt-PT").as_deref(), Some("portuguese"));
+        assert_eq!(language_for_code("es-MX").as_deref(), Some("mexican"));
+        assert_eq!(language_for_code("Japanese").as_deref(), Some("japanese"));
+        assert_eq!(language_for_code("elvish"), None);
+    }
+
+    fn fixture() -> ModFixture {
+        ModFixture::new(&["$MyMod_Title", "$MyMod_Help"])
+            .file("mcm-meta-helper.toml", "required_languages = []\n")
+            .translation(
+                "MyMod_english.txt",
+                &["$MyMod_Title\tTitle", "$MyMod_Help\tHelp, \"quoted\""],
+            )
+            .translation(
+                "MyMod_german.txt",
+                &["$MyMod_Title\tTitel", "$MyMod_Help\tHilfe, „zitiert“"],
+            )
+    }
+
+    fn args(fixture: &ModFixture) -> Args {
+        Args::parse_from([
+            "mcm-meta-helper",
+            "--moddir",
+            fixture.path().to_str().unwrap(),
+            "lsp",
+        ])
+    }
+
+    fn german(fixture: &ModFixture) -> HashMap<String, String> {
+        let mut moddir = fixture.moddir();
+        let mut trfile = moddir.translation_file_for("german").unwrap().unwrap();
+        trfile.translations().unwrap()
+    }
+
+    fn round_trip(format: ExchangeFormat, exported: &str) {
+        let fixture = fixture();
+        let args = args(&fixture);
+        let output = fixture.path().join("exported");
+        export(&args, format, &output).unwrap();
+        let before = german(&fixture);
+
+        let fixture = fixture.translation(
+            "MyMod_german.txt",
+            &["$MyMod_Title\ttranslation for MyMod_Title"],
+        );
+        assert!(import(&args, &[output.join(exported)], false).unwrap());
+        assert_eq!(german(&fixture), before);
+    }
+
+    #[test]
+    fn po_round_trip() {
+        round_trip(ExchangeFormat::Po, "MyMod_german.po");
+    }
+
+    #[test]
+    fn xliff_round_trip() {
+        round_trip(ExchangeFormat::Xliff, "MyMod_german.xlf");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        round_trip(ExchangeFormat::Csv, "MyMod.csv");
+    }
+
+    #[test]
+    fn a_bad_file_stops_the_import_before_anything_is_written() {
+        let fixture = fixture();
+        let args = args(&fixture);
+        let output = fixture.path().join("exported");
+        export(&args, ExchangeFormat::Po, &output).unwrap();
+        let fixture = fixture.translation("MyMod_german.txt", &["$MyMod_Title\tAlt"]);
+        let good = output.join("MyMod_german.po");
+
+        let unknown = output.join("notes.txt");
+        std::fs::write(&unknown, "not a translation").unwrap();
+        assert!(import(&args, &[good.clone(), unknown], false).is_err());
+        assert_eq!(german(&fixture)["$MyMod_Title"], "Alt");
+
+        let french = output.join("MyMod_french.po");
+        std::fs::write(
+            &french,
+            std::fs::read_to_string(&good)
+                .unwrap()
+                .replace("german", "french")
+                .replace("Language: de", "Language: fr"),
+        )
+        .unwrap();
+        assert!(import(&args, &[good, french], false).is_err());
+        assert_eq!(german(&fixture)["$MyMod_Title"], "Alt");
+    }
+}
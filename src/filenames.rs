@@ -0,0 +1,82 @@
+//! Translation files are only loaded by the game if they're named
+//! `<PluginName>_<language>.txt`. Check that the files we found follow the
+//! convention closely enough to work.
+
+use std::path::Path;
+
+use eyre::Result;
+
+use crate::{ModDirectory, SKYRIM_LANGUAGES};
+
+/// A translation file whose name will keep it from being loaded.
+#[derive(Debug, Clone)]
+pub struct FilenameProblem {
+    /// The file's name, without its directory.
+    pub filename: String,
+    /// The language the file appears to be for, if we could tell.
+    pub language: Option<String>,
+    /// What's wrong with the name.
+    pub problem: String,
+}
+
+/// Split a translation file's name into its plugin name and its language. The
+/// language is whatever follows the last underscore, so plugin names may contain
+/// underscores. Languages are lowercased, because the game doesn't care about case.
+pub fn split_translation_filename(fpath: &Path) -> Option<(String, String)> {
+    let stem = fpath.file_stem()?.to_string_lossy();
+    let (plugin, language) = stem.rsplit_once('_')?;
+    if plugin.is_empty() || language.is_empty() {
+        return None;
+    }
+    Some((plugin.to_string(), language.to_lowercase()))
+}
+
+/// Check every file in the translations directory against the plugin names in
+/// the data directory and the `modName` in config.json.
+pub fn misnamed_translation_files(moddir: &mut ModDirectory) -> Result<Vec<FilenameProblem>> {
+    let mut expected = moddir.plugin_names()?;
+    if let Some(modname) = moddir.mcm_mod_name()? {
+        if !expected.iter().any(|xs| xs.eq_ignore_ascii_case(&modname)) {
+            expected.push(modname);
+        }
+    }
+    let required = moddir.config().required_languages.clone();
+
+    let mut problems = Vec::new();
+    for fpath in moddir.translation_file_paths()? {
+        let filename = fpath
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let Some((plugin, language)) = split_translation_filename(&fpath) else {
+            problems.push(FilenameProblem {
+                filename,
+                language: None,
+                problem: "does not end in _<language>.txt".to_string(),
+            });
+            continue;
+        };
+
+        if !SKYRIM_LANGUAGES.contains(&language.as_str()) && !required.contains(&language) {
+            problems.push(FilenameProblem {
+                filename: filename.clone(),
+                language: Some(language.clone()),
+                problem: format!("`{language}` is not a language Skyrim loads"),
+            });
+        }
+
+        if !expected.is_empty() && !expected.iter().any(|xs| xs.eq_ignore_ascii_case(&plugin)) {
+            problems.push(FilenameProblem {
+                filename,
+                language: Some(language),
+                problem: format!(
+                    "`{plugin}` matches no plugin or modName; expected {}",
+                    expected.join(" or ")
+                ),
+            });
+        }
+    }
+
+    Ok(problems)
+}
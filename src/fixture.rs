@@ -0,0 +1,76 @@
+//! Small mod directories on disk for tests to run against.
+
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use crate::{ModDirectory, Translation};
+
+/// A mod with a data directory, an MCM config, and whatever translation files
+/// and other files a test adds. Everything is deleted when it's dropped.
+pub struct ModFixture {
+    dir: TempDir,
+}
+
+impl ModFixture {
+    /// A mod named `MyMod` whose config.json requests these keys, one per option.
+    pub fn new(keys: &[&str]) -> Self {
+        let content: Vec<String> = keys
+            .iter()
+            .map(|key| format!("{{ \"text\": \"{key}\", \"type\": \"text\" }}"))
+            .collect();
+        let config = format!(
+            "{{\n  \"modName\": \"MyMod\",\n  \"pages\": [\n    {{ \"pageDisplayName\": \"MyMod\", \"content\": [\n      {}\n    ] }}\n  ]\n}}\n",
+            content.join(",\n      ")
+        );
+        Self::with_config(&config)
+    }
+
+    /// A mod named `MyMod` with this text as its config.json.
+    pub fn with_config(config: &str) -> Self {
+        let fixture = Self {
+            dir: tempfile::tempdir().expect("creating a temporary directory"),
+        };
+        std::fs::create_dir_all(fixture.translations_dir()).expect("creating the data directory");
+        fixture.file("data/mcm/config/MyMod/config.json", config)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn translations_dir(&self) -> PathBuf {
+        self.path().join("data/Interface/Translations")
+    }
+
+    /// Add a file, relative to the mod root.
+    pub fn file(self, relative: &str, contents: &str) -> Self {
+        let fpath = self.path().join(relative);
+        if let Some(parent) = fpath.parent() {
+            std::fs::create_dir_all(parent).expect("creating a fixture directory");
+        }
+        std::fs::write(&fpath, contents).expect("writing a fixture file");
+        self
+    }
+
+    /// Add a translation file with these lines, which are written as they are.
+    pub fn translation(self, filename: &str, lines: &[&str]) -> Self {
+        let fpath = self.translations_dir().join(filename);
+        let mut trfile = Translation::new(fpath, "fixture");
+        trfile.set_lines(
+            lines
+                .iter()
+                .map(|line| match line.split_once('\t') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (line.to_string(), String::new()),
+                })
+                .collect(),
+        );
+        trfile.write().expect("writing a fixture translation file");
+        self
+    }
+
+    pub fn moddir(&self) -> ModDirectory {
+        ModDirectory::new(&self.path().to_string_lossy()).expect("loading the fixture mod")
+    }
+}
@@ -0,0 +1,324 @@
+//! Rewrite translation files in a canonical layout, so every language file
+//! has the same order, spacing, and section comments. Formatting never adds
+//! or removes translations or comments; it only moves them around.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{directive_text, Translation};
+
+/// How to order the translations in a formatted file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FormatOrder {
+    /// Mirror the source language file, including its comments and blank lines.
+    Source,
+    /// Group translations by the config.json page that uses them.
+    Pages,
+}
+
+/// One line of the layout every formatted file follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutLine {
+    Blank,
+    Comment(String),
+    Key(String),
+}
+
+/// A layout that mirrors the source language file line for line. Directive
+/// comments belong to the source file, so they are left out.
+pub fn layout_from_source(source: &Translation) -> Vec<LayoutLine> {
+    source
+        .lines()
+        .iter()
+        .filter_map(|(key, value)| {
+            if !value.is_empty() {
+                Some(LayoutLine::Key(key.trim().to_string()))
+            } else if key.trim().is_empty() {
+                Some(LayoutLine::Blank)
+            } else if directive_text(key).is_some() {
+                None
+            } else {
+                Some(LayoutLine::Comment(key.trim().to_string()))
+            }
+        })
+        .collect()
+}
+
+/// A layout with one commented section per page, given each section's title and keys.
+pub fn layout_from_sections(sections: &[(String, Vec<String>)]) -> Vec<LayoutLine> {
+    let mut layout = Vec::new();
+    for (title, keys) in sections {
+        layout.push(LayoutLine::Blank);
+        layout.push(LayoutLine::Comment(section_comment(title)));
+        layout.extend(keys.iter().map(|key| LayoutLine::Key(key.clone())));
+    }
+    layout
+}
+
+fn section_comment(title: &str) -> String {
+    format!("; ---------- {title} ----------")
+}
+
+fn is_section_comment(text: &str) -> bool {
+    text.starts_with("; ----------")
+}
+
+/// A translation and the lines before it that go with it: directive comments
+/// that apply to it, and the file's own comments.
+struct Entry {
+    attached: Vec<String>,
+    key: String,
+    value: String,
+}
+
+/// Lay out a translation file's entries following the given layout. Entries the
+/// layout doesn't mention go at the end in their own section, in file order.
+/// Directives that name their keys go at the top of the file. Comments and
+/// other lines that aren't translations travel with the entry after them,
+/// unless the layout already has them; any after the last entry stay at the end.
+pub fn formatted_lines(trfile: &Translation, layout: &[LayoutLine]) -> Vec<(String, String)> {
+    let layout_comments: HashSet<&str> = layout
+        .iter()
+        .filter_map(|line| match line {
+            LayoutLine::Comment(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut header: Vec<String> = Vec::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+    for (key, value) in trfile.lines() {
+        let text = key.trim();
+        if !value.is_empty() {
+            entries.push(Entry {
+                attached: std::mem::take(&mut pending),
+                key: text.to_string(),
+                value: value.trim().to_string(),
+            });
+        } else if let Some(directive) = directive_text(key) {
+            // A directive that names no keys applies to the next entry.
+            if directive.split_whitespace().count() <= 1 {
+                pending.push(text.to_string());
+            } else {
+                header.push(text.to_string());
+            }
+        } else if !text.is_empty() && !layout_comments.contains(text) && !is_section_comment(text) {
+            pending.push(text.to_string());
+        }
+    }
+
+    let mut by_key: HashMap<&str, Vec<&Entry>> = HashMap::new();
+    for entry in entries.iter() {
+        by_key.entry(entry.key.as_str()).or_default().push(entry);
+    }
+
+    let mut output: Vec<(String, String)> = header.into_iter().map(comment).collect();
+    let mut placed: HashSet<&str> = HashSet::new();
+    for line in layout {
+        match line {
+            LayoutLine::Blank => output.push(blank()),
+            LayoutLine::Comment(text) => output.push(comment(text.clone())),
+            LayoutLine::Key(key) => {
+                if !placed.insert(key.as_str()) {
+                    continue;
+                }
+                if let Some(found) = by_key.get(key.as_str()) {
+                    for entry in found {
+                        push_entry(&mut output, entry);
+                    }
+                }
+            }
+        }
+    }
+
+    let leftovers: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| !placed.contains(entry.key.as_str()))
+        .collect();
+    if !leftovers.is_empty() {
+        output.push(blank());
+        output.push(comment(section_comment("other")));
+        for entry in leftovers {
+            push_entry(&mut output, entry);
+        }
+    }
+    if !pending.is_empty() {
+        output.push(blank());
+        output.extend(pending.into_iter().map(comment));
+    }
+
+    tidy(output)
+}
+
+fn push_entry(output: &mut Vec<(String, String)>, entry: &Entry) {
+    output.extend(entry.attached.iter().cloned().map(comment));
+    output.push((entry.key.clone(), entry.value.clone()));
+}
+
+fn blank() -> (String, String) {
+    (String::new(), String::new())
+}
+
+fn comment(text: String) -> (String, String) {
+    (text, String::new())
+}
+
+/// Drop section comments with nothing under them, collapse runs of blank lines,
+/// and trim blank lines from the start and the end. The line ending after the
+/// last line isn't a line of its own; formatted files get one when written.
+fn tidy(lines: Vec<(String, String)>) -> Vec<(String, String)> {
+    let is_blank = |line: &(String, String)| line.0.is_empty() && line.1.is_empty();
+    let is_section = |line: &(String, String)| line.1.is_empty() && is_section_comment(&line.0);
+
+    let mut kept: Vec<(String, String)> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if is_section(line) {
+            let has_entries = lines[idx + 1..]
+                .iter()
+                .take_while(|next| !is_section(next))
+                .any(|next| !next.1.is_empty());
+            if !has_entries {
+                continue;
+            }
+        }
+        if is_blank(line) && kept.last().map(is_blank).unwrap_or(true) {
+            continue;
+        }
+        kept.push(line.clone());
+    }
+    while kept.last().map(is_blank).unwrap_or(false) {
+        kept.pop();
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(lines: &[&str]) -> Translation {
+        let mut trfile = Translation::new("MyMod_german.txt".into(), "german");
+        trfile.set_lines(
+            lines
+                .iter()
+                .map(|line| match line.split_once('\t') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (line.to_string(), String::new()),
+                })
+                .collect(),
+        );
+        trfile
+    }
+
+    fn text(lines: &[(String, String)]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|(key, value)| {
+                if value.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{key}\t{value}")
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn follows_the_source_layout() {
+        let source = translation(&["; General", "$A\tA", "", "; Advanced", "$B\tB", "$C\tC"]);
+        let target = translation(&["$C\tc", "$A\ta", "$B\tb"]);
+        let formatted = formatted_lines(&target, &layout_from_source(&source));
+        assert_eq!(
+            text(&formatted),
+            ["; General", "$A\ta", "", "; Advanced", "$B\tb", "$C\tc"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_files_own_comments_and_stray_lines() {
+        let source = translation(&["; General", "$A\tA", "$B\tB"]);
+        let target = translation(&[
+            "; General",
+            "$B\tb",
+            "; translator's note about A",
+            "$A\ta",
+            "a stray line",
+            "; the end",
+        ]);
+        let layout = layout_from_source(&source);
+        let formatted = formatted_lines(&target, &layout);
+        assert_eq!(
+            text(&formatted),
+            [
+                "; General",
+                "; translator's note about A",
+                "$A\ta",
+                "$B\tb",
+                "",
+                "a stray line",
+                "; the end"
+            ]
+        );
+
+        // Formatting a formatted file changes nothing.
+        let again = formatted_lines(
+            &translation(
+                &text(&formatted)
+                    .iter()
+                    .map(|xs| xs.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            &layout,
+        );
+        assert_eq!(again, formatted);
+    }
+
+    #[test]
+    fn directives_travel_with_their_entries() {
+        let source = translation(&["$A\tA", "$B\tB"]);
+        let target = translation(&[
+            "; mcm-meta-helper: allow-missing $C",
+            "$B\tb",
+            "; mcm-meta-helper: allow-unused",
+            "$A\ta",
+        ]);
+        let formatted = formatted_lines(&target, &layout_from_source(&source));
+        assert_eq!(
+            text(&formatted),
+            [
+                "; mcm-meta-helper: allow-missing $C",
+                "; mcm-meta-helper: allow-unused",
+                "$A\ta",
+                "$B\tb"
+            ]
+        );
+    }
+
+    #[test]
+    fn pages_layout_puts_leftovers_last_and_drops_empty_sections() {
+        let sections = vec![
+            ("General".to_string(), vec!["$A".to_string()]),
+            ("Empty".to_string(), vec!["$Missing".to_string()]),
+        ];
+        let target = translation(&["$Extra\textra", "$A\ta"]);
+        let formatted = formatted_lines(&target, &layout_from_sections(&sections));
+        assert_eq!(
+            text(&formatted),
+            [
+                "; ---------- General ----------",
+                "$A\ta",
+                "",
+                "; ---------- other ----------",
+                "$Extra\textra"
+            ]
+        );
+    }
+
+    #[test]
+    fn trims_and_collapses_blank_lines() {
+        let source = translation(&["", "", "$A\tA", "", "", "$B\tB", "", ""]);
+        let target = translation(&["$A\ta", "$B\tb"]);
+        let formatted = formatted_lines(&target, &layout_from_source(&source));
+        assert_eq!(text(&formatted), ["$A\ta", "", "$B\tb"]);
+    }
+}
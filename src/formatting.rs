@@ -29,6 +29,14 @@ pub fn grid_string(items: &Vec<impl ToString>, space: u16) -> String {
     }
 }
 
+/// The name clap uses for an enum value on the command line.
+fn value_name(value: &impl clap::ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|xs| xs.get_name().to_string())
+        .unwrap_or_default()
+}
+
 impl std::fmt::Display for Args {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "mcm-meta-helper ")?;
@@ -48,16 +56,120 @@ impl std::fmt::Display for Args {
 impl std::fmt::Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Command::Check { ref language, .. } => {
-                if (*language).as_str() == "all" {
-                    write!(f, "check --all")
+            Command::Check(ref opts) => {
+                if opts.language.as_str() == "all" {
+                    write!(f, "check --all")?;
                 } else {
-                    write!(f, "check --language {language}")
+                    write!(f, "check --language {}", opts.language)?;
+                }
+                if opts.fix {
+                    write!(f, " --fix")?;
+                }
+                if opts.write_suppressions {
+                    write!(f, " --write-suppressions")?;
+                }
+                if let Some(ref baseline) = opts.baseline {
+                    write!(f, " --baseline '{}'", baseline.display())?;
+                }
+                if let Some(ref against) = opts.against {
+                    write!(f, " --against '{}'", against.display())?;
+                }
+                if opts.watch {
+                    write!(f, " --watch")?;
+                }
+                Ok(())
+            }
+            Command::Copy { ref language } => {
+                if let Some(language) = language {
+                    write!(f, "copy {language}")
+                } else {
+                    write!(f, "copy")
+                }
+            }
+            Command::Update { create_missing } => {
+                if *create_missing {
+                    write!(f, "update --create-missing")
+                } else {
+                    write!(f, "update")
+                }
+            }
+            Command::Dedupe { ref language, keep } => {
+                write!(f, "dedupe {language} --keep {}", value_name(keep))
+            }
+            Command::Format {
+                ref language,
+                order,
+                check,
+            } => {
+                write!(f, "format {language} --order {}", value_name(order))?;
+                if *check {
+                    write!(f, " --check")?;
+                }
+                Ok(())
+            }
+            Command::Prune { dry_run, yes } => {
+                write!(f, "prune")?;
+                if *dry_run {
+                    write!(f, " --dry-run")?;
+                }
+                if *yes {
+                    write!(f, " --yes")?;
+                }
+                Ok(())
+            }
+            Command::RenameKey { old, new, dry_run } => {
+                write!(f, "rename-key {old} {new}")?;
+                if *dry_run {
+                    write!(f, " --dry-run")?;
+                }
+                Ok(())
+            }
+            Command::FixNames { dry_run } => {
+                write!(f, "fix-names")?;
+                if *dry_run {
+                    write!(f, " --dry-run")?;
+                }
+                Ok(())
+            }
+            Command::Collisions { path } => write!(f, "collisions {}", path.display()),
+            Command::Stats { json } => {
+                if *json {
+                    write!(f, "stats --json")
+                } else {
+                    write!(f, "stats")
+                }
+            }
+            Command::Validate { watch } => {
+                write!(f, "validate")?;
+                if *watch {
+                    write!(f, " --watch")?;
+                }
+                Ok(())
+            }
+            Command::Edit { ref languages } => {
+                write!(f, "edit")?;
+                for language in languages {
+                    write!(f, " {language}")?;
+                }
+                Ok(())
+            }
+            Command::Export { format, output } => write!(
+                f,
+                "export --format {} --output '{}'",
+                value_name(format),
+                output.display()
+            ),
+            Command::Import { files, dry_run } => {
+                write!(f, "import")?;
+                for file in files {
+                    write!(f, " '{}'", file.display())?;
+                }
+                if *dry_run {
+                    write!(f, " --dry-run")?;
                 }
+                Ok(())
             }
-            Command::Copy { ref language } => write!(f, "copy {language}"),
-            Command::Update => write!(f, "update"),
-            Command::Validate => write!(f, "validate"),
+            Command::Lsp => write!(f, "lsp"),
         }
     }
 }
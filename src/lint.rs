@@ -0,0 +1,291 @@
+//! Lint translated values against the source language's values. Placeholders,
+//! nested translation references, format specifiers, and markup tags are all
+//! interpreted by the game or by SkyUI, so a translation must keep every one
+//! of them that its source has, even if it moves them around.
+
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::is_stub;
+
+/// Nested translation references, like `$MyMod_Other{}`.
+static KEY_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$[^\s{}$]+\{[^{}]*\}").expect("key reference regex is valid"));
+/// Substitution placeholders, like `{value}` and `{0}`.
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{[^{}]*\}").expect("placeholder regex is valid"));
+/// Format specifiers and bare percent signs, as used by `formatString`.
+static FORMAT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%(?:[-+#0]*\d*(?:\.\d+)?[a-zA-Z]|%)?").expect("format specifier regex is valid")
+});
+/// Markup tags, like `<font color='#ff0000'>` and `</font>`. Only the tag name counts.
+static TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9]*)[^>]*>").expect("tag regex is valid"));
+
+/// A translated value whose tokens don't match the source language's value.
+#[derive(Debug, Clone)]
+pub struct PlaceholderMismatch {
+    pub key: String,
+    /// Tokens in the source value that the translation lacks.
+    pub missing: Vec<String>,
+    /// Tokens in the translation that the source value lacks.
+    pub unexpected: Vec<String>,
+}
+
+impl std::fmt::Display for PlaceholderMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.key)?;
+        if !self.missing.is_empty() {
+            write!(f, " missing {}", self.missing.join(" "))?;
+        }
+        if !self.unexpected.is_empty() {
+            if !self.missing.is_empty() {
+                write!(f, ";")?;
+            }
+            write!(f, " unexpected {}", self.unexpected.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Find every token in a value that must survive translation, sorted.
+pub fn value_tokens(value: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = KEY_REFERENCE
+        .find_iter(value)
+        .map(|m| m.as_str().to_string())
+        .collect();
+    let remainder = KEY_REFERENCE.replace_all(value, " ");
+
+    tokens.extend(
+        PLACEHOLDER
+            .find_iter(&remainder)
+            .map(|m| m.as_str().to_string()),
+    );
+    tokens.extend(FORMAT.find_iter(&remainder).map(|m| m.as_str().to_string()));
+    tokens.extend(
+        TAG.captures_iter(&remainder)
+            .map(|caps| format!("<{}{}>", &caps[1], caps[2].to_lowercase())),
+    );
+    tokens.sort();
+    tokens
+}
+
+/// Compare every translated value with the source value for the same key. Keys
+/// the source language doesn't have are skipped; they're reported elsewhere.
+pub fn placeholder_mismatches(
+    source: &HashMap<String, String>,
+    target: &HashMap<String, String>,
+) -> Vec<PlaceholderMismatch> {
+    let mut mismatches: Vec<PlaceholderMismatch> = target
+        .iter()
+        .filter_map(|(key, value)| {
+            let source_value = source.get(key)?;
+            if is_stub(key, value) {
+                return None;
+            }
+            let expected = value_tokens(source_value);
+            let found = value_tokens(value);
+            if expected == found {
+                return None;
+            }
+            Some(PlaceholderMismatch {
+                key: key.clone(),
+                missing: multiset_difference(&expected, &found),
+                unexpected: multiset_difference(&found, &expected),
+            })
+        })
+        .collect();
+    mismatches.sort_by(|a, b| a.key.cmp(&b.key));
+    mismatches
+}
+
+/// A requested key that a translation file provides only with different capitalization.
+#[derive(Debug, Clone)]
+pub struct CaseMismatch {
+    /// The key as requested by a json file.
+    pub requested: String,
+    /// The key as spelled in the translation file.
+    pub provided: String,
+}
+
+/// Pair up requested keys missing from a translation file with provided keys
+/// that differ from them only in case. Sorted by requested key.
+pub fn case_mismatches(missing: &[String], provided: &HashSet<String>) -> Vec<CaseMismatch> {
+    let folded: HashMap<String, &String> = provided
+        .iter()
+        .map(|xs| (xs.to_lowercase(), xs))
+        .collect();
+    let mut found: Vec<CaseMismatch> = missing
+        .iter()
+        .filter_map(|requested| {
+            let provided = folded.get(&requested.to_lowercase())?;
+            Some(CaseMismatch {
+                requested: requested.clone(),
+                provided: (*provided).clone(),
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.requested.cmp(&b.requested));
+    found
+}
+
+/// Why a value counts as untranslated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Untranslated {
+    /// The stub value written by `update`.
+    Stub,
+    /// The same text as the source language, probably from `copy`.
+    SameAsSource,
+}
+
+/// Find the values in a target language that nobody has translated yet, sorted by key.
+/// The source language itself can only have stubs.
+pub fn untranslated_values(
+    source: &HashMap<String, String>,
+    target: &HashMap<String, String>,
+    is_source: bool,
+) -> Vec<(String, Untranslated)> {
+    let mut found: Vec<(String, Untranslated)> = target
+        .iter()
+        .filter_map(|(key, value)| {
+            if is_stub(key, value) {
+                Some((key.clone(), Untranslated::Stub))
+            } else if !is_source && source.get(key) == Some(value) {
+                Some((key.clone(), Untranslated::SameAsSource))
+            } else {
+                None
+            }
+        })
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+/// The percentage of required keys that have a real translation.
+pub fn completion_percent(required: usize, missing: usize, untranslated: usize) -> f64 {
+    if required == 0 {
+        return 100.0;
+    }
+    let done = required.saturating_sub(missing + untranslated);
+    done as f64 * 100.0 / required as f64
+}
+
+/// Items in the left sorted list that the right sorted list doesn't account for,
+/// counting duplicates.
+fn multiset_difference(left: &[String], right: &[String]) -> Vec<String> {
+    let mut remaining: Vec<&String> = right.iter().collect();
+    left.iter()
+        .filter(|item| {
+            if let Some(idx) = remaining.iter().position(|xs| xs == item) {
+                remaining.swap_remove(idx);
+                false
+            } else {
+                true
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_kind_of_token() {
+        assert_eq!(
+            value_tokens(
+                "Set {value} to %d%% with $MyMod_Unit{} in <FONT color='#ff0000'>red</font>"
+            ),
+            ["$MyMod_Unit{}", "%%", "%d", "</font>", "<font>", "{value}"]
+        );
+        assert!(value_tokens("Plain text").is_empty());
+    }
+
+    #[test]
+    fn tokens_may_move_but_must_all_be_there() {
+        let source = map(&[
+            ("$A", "{0} of {1}"),
+            ("$B", "Costs %d gold"),
+            ("$C", "<b>Bold</b>"),
+            ("$D", "Only in the source"),
+        ]);
+        let target = map(&[
+            ("$A", "{1} von {0}"),
+            ("$B", "Kostet Gold"),
+            ("$C", "<b>Fett</b> {extra}"),
+            ("$E", "Not in the source {0}"),
+        ]);
+        let mismatches = placeholder_mismatches(&source, &target);
+        let described: Vec<String> = mismatches.iter().map(|xs| xs.to_string()).collect();
+        assert_eq!(described, ["$B: missing %d", "$C: unexpected {extra}"]);
+    }
+
+    #[test]
+    fn counts_repeated_tokens() {
+        let source = map(&[("$A", "{0} and {0}")]);
+        let target = map(&[("$A", "{0} und so")]);
+        let mismatches = placeholder_mismatches(&source, &target);
+        assert_eq!(mismatches[0].missing, ["{0}"]);
+        assert!(mismatches[0].unexpected.is_empty());
+    }
+
+    #[test]
+    fn stubs_are_not_mismatches() {
+        let source = map(&[("$MyMod_A", "{0} items")]);
+        let target = map(&[("$MyMod_A", "translation for MyMod_A")]);
+        assert!(placeholder_mismatches(&source, &target).is_empty());
+    }
+
+    #[test]
+    fn stubs_and_copies_are_untranslated() {
+        let source = map(&[
+            ("$MyMod_A", "Apple"),
+            ("$MyMod_B", "Banana"),
+            ("$MyMod_C", "translation for MyMod_C"),
+        ]);
+        let target = map(&[
+            ("$MyMod_A", "Apfel"),
+            ("$MyMod_B", "Banana"),
+            ("$MyMod_C", "translation for MyMod_C"),
+        ]);
+        assert_eq!(
+            untranslated_values(&source, &target, false),
+            [
+                ("$MyMod_B".to_string(), Untranslated::SameAsSource),
+                ("$MyMod_C".to_string(), Untranslated::Stub),
+            ]
+        );
+        // The source language can't be a copy of itself.
+        assert_eq!(
+            untranslated_values(&source, &source, true),
+            [("$MyMod_C".to_string(), Untranslated::Stub)]
+        );
+    }
+
+    #[test]
+    fn completion_counts_missing_and_untranslated_keys() {
+        assert_eq!(completion_percent(4, 1, 1), 50.0);
+        assert_eq!(completion_percent(0, 0, 0), 100.0);
+        assert_eq!(completion_percent(2, 2, 1), 0.0);
+    }
+
+    #[test]
+    fn pairs_keys_that_differ_only_in_case() {
+        let missing = vec!["$MyMod_Title".to_string(), "$MyMod_Help".to_string()];
+        let provided = HashSet::from(["$mymod_title".to_string(), "$MyMod_Other".to_string()]);
+        let found = case_mismatches(&missing, &provided);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].requested, "$MyMod_Title");
+        assert_eq!(found[0].provided, "$mymod_title");
+    }
+}
@@ -0,0 +1,870 @@
+//! A language server for config.json and translation files, speaking LSP over
+//! stdio. It reports the same missing and unused keys `check` does, plus schema
+//! errors in config.json, and helps with keys: hover to see a key's text in
+//! every language, jump from a key to its translations, and complete keys.
+//!
+//! Diagnostics are computed from the files on disk, so they update when a file
+//! is opened or saved. Hover, definition, and completion use the text being edited.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use eyre::{Report, Result};
+use jsonschema::JSONSchema;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    CompletionTextEdit, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, InitializeParams, Location, MarkupContent, MarkupKind, OneOf,
+    Position, PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit, Url,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    confirmed_unused, inline_suppressions, mcm_schema, split_translation_filename, Args,
+    ModDirectory, ProblemKind, Suppressions, Translation,
+};
+
+/// Translation keys inside json strings.
+static JSON_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#""(\$[^"\\]*)""#).expect("json key regex is valid"));
+
+/// Run the language server until the client shuts it down.
+pub fn lsp(args: &Args) -> Result<bool, Report> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["$".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+
+    // The client's workspace is the mod directory, if it tells us what that is.
+    #[allow(deprecated)]
+    let root = params
+        .workspace_folders
+        .and_then(|folders| folders.first().map(|xs| xs.uri.clone()))
+        .or(params.root_uri)
+        .and_then(|uri| uri.to_file_path().ok());
+    let directory = root
+        .map(|xs| xs.display().to_string())
+        .unwrap_or_else(|| args.moddir.clone());
+
+    let mut server = Server {
+        moddir: ModDirectory::new(directory.as_str())?,
+        schema: mcm_schema()?,
+        documents: HashMap::new(),
+    };
+    log::info!("Serving translation help for {directory}");
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = server.respond(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                let published = match server.notice(notification) {
+                    Ok(published) => published,
+                    Err(e) => {
+                        // One bad file shouldn't take the server down with it.
+                        log::warn!("{e:#}");
+                        continue;
+                    }
+                };
+                for (uri, diagnostics) in published {
+                    let params = PublishDiagnosticsParams {
+                        uri,
+                        diagnostics,
+                        version: None,
+                    };
+                    connection
+                        .sender
+                        .send(Message::Notification(Notification::new(
+                            PublishDiagnostics::METHOD.to_string(),
+                            params,
+                        )))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    // The writer thread finishes once nothing can send to it any more.
+    drop(connection);
+    io_threads.join()?;
+    Ok(true)
+}
+
+struct Server {
+    moddir: ModDirectory,
+    schema: JSONSchema,
+    /// The text of every open document, as the editor has it.
+    documents: HashMap<Url, String>,
+}
+
+/// What kind of file a document is, as far as we're concerned.
+enum DocumentKind {
+    /// A json file that requests translations. The flag is true for config.json.
+    KeySource(bool),
+    /// A translation file for a language.
+    Translations(String),
+    Other,
+}
+
+impl Server {
+    fn respond(&mut self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => extract::<HoverParams>(request)
+                .and_then(|params| self.hover(params))
+                .and_then(|xs| Ok(serde_json::to_value(xs)?)),
+            GotoDefinition::METHOD => extract::<GotoDefinitionParams>(request)
+                .and_then(|params| self.definition(params))
+                .and_then(|xs| Ok(serde_json::to_value(xs)?)),
+            Completion::METHOD => extract::<CompletionParams>(request)
+                .and_then(|params| self.completion(params))
+                .and_then(|xs| Ok(serde_json::to_value(xs)?)),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("{method} is not supported"),
+                )
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(e) => Response::new_err(id, ErrorCode::InternalError as i32, format!("{e:#}")),
+        }
+    }
+
+    /// Keep track of open documents, returning any diagnostics to publish.
+    fn notice(&mut self, notification: Notification) -> Result<Vec<(Url, Vec<Diagnostic>)>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                Ok(vec![(uri.clone(), self.diagnostics(&uri)?)])
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(params.text_document.uri, change.text);
+                }
+                Ok(Vec::new())
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: DidSaveTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if let Some(path) = file_path(&params.text_document.uri) {
+                    self.moddir.forget(&[path]);
+                }
+                // Saving one file can change what's missing or unused in the others.
+                let open: Vec<Url> = self.documents.keys().cloned().collect();
+                open.into_iter()
+                    .map(|uri| Ok((uri.clone(), self.diagnostics(&uri)?)))
+                    .collect()
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Ok(vec![(params.text_document.uri, Vec::new())])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn kind_of(&mut self, uri: &Url) -> Result<DocumentKind> {
+        let Some(path) = file_path(uri) else {
+            return Ok(DocumentKind::Other);
+        };
+        if path.parent() == Some(self.moddir.translations_dir().as_path()) {
+            if let Some((_plugin, language)) = split_translation_filename(&path) {
+                return Ok(DocumentKind::Translations(language));
+            }
+        }
+        if self.moddir.key_source_files()?.contains(&path) {
+            let is_config = self.moddir.find_config()?.as_ref() == Some(&path);
+            return Ok(DocumentKind::KeySource(is_config));
+        }
+        Ok(DocumentKind::Other)
+    }
+
+    fn diagnostics(&mut self, uri: &Url) -> Result<Vec<Diagnostic>> {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        match self.kind_of(uri)? {
+            DocumentKind::KeySource(is_config) => {
+                let mut found = self.missing_key_diagnostics(text.as_str())?;
+                if is_config {
+                    found.extend(self.schema_diagnostics(text.as_str()));
+                }
+                Ok(found)
+            }
+            DocumentKind::Translations(language) => self.translation_diagnostics(language.as_str()),
+            DocumentKind::Other => Ok(Vec::new()),
+        }
+    }
+
+    /// Warn about every key in a json file that some language doesn't translate.
+    fn missing_key_diagnostics(&mut self, text: &str) -> Result<Vec<Diagnostic>> {
+        let mut provided: Vec<(String, HashSet<String>, Suppressions)> = Vec::new();
+        for (language, mut trfile) in sorted_translations(&mut self.moddir)? {
+            let mut inline = Suppressions::default();
+            inline.extend(inline_suppressions(language.as_str(), trfile.lines()));
+            let keys = HashSet::from_iter(trfile.provided_translations()?);
+            provided.push((language, keys, inline));
+        }
+
+        let mut found = Vec::new();
+        for caps in JSON_KEY.captures_iter(text) {
+            let whole = caps.get(1).expect("the key is captured");
+            let key = whole.as_str().trim();
+            if self.moddir.builtin_keys().contains(key) {
+                continue;
+            }
+            let lacking: Vec<&str> = provided
+                .iter()
+                .filter(|(language, keys, inline)| {
+                    !keys.contains(key)
+                        && !inline.allows(ProblemKind::Missing, language, key)
+                        && !self
+                            .moddir
+                            .is_suppressed(ProblemKind::Missing, language, key)
+                })
+                .map(|(language, _keys, _inline)| language.as_str())
+                .collect();
+            if lacking.is_empty() {
+                continue;
+            }
+            found.push(diagnostic(
+                Range::new(
+                    position_at(text, whole.start()),
+                    position_at(text, whole.end()),
+                ),
+                DiagnosticSeverity::WARNING,
+                format!("{key} has no translation in {}", lacking.join(", ")),
+            ));
+        }
+        Ok(found)
+    }
+
+    /// Report schema errors in config.json where the schema found them.
+    fn schema_diagnostics(&self, text: &str) -> Vec<Diagnostic> {
+        let Ok(config) = serde_json::from_str::<serde_json::Value>(text) else {
+            return vec![diagnostic(
+                Range::default(),
+                DiagnosticSeverity::ERROR,
+                "This file is not valid json.".to_string(),
+            )];
+        };
+        let Err(errors) = self.schema.validate(&config) else {
+            return Vec::new();
+        };
+        errors
+            .map(|error| {
+                let path = error.instance_path.clone().into_vec();
+                let start = locate_pointer(text, &path).unwrap_or(0);
+                let position = position_at(text, start);
+                let line_end = text[start..]
+                    .find('\n')
+                    .map(|xs| start + xs)
+                    .unwrap_or(text.len());
+                diagnostic(
+                    Range::new(position, position_at(text, line_end)),
+                    DiagnosticSeverity::ERROR,
+                    format!("{error} (at {})", error.instance_path),
+                )
+            })
+            .collect()
+    }
+
+    /// Report the unused keys in a translation file where they are, and its
+    /// missing keys at the top.
+    fn translation_diagnostics(&mut self, language: &str) -> Result<Vec<Diagnostic>> {
+        let Some(mut trfile) = self.moddir.translation_files()?.remove(language) else {
+            return Ok(Vec::new());
+        };
+        let requested = HashSet::from_iter(self.moddir.all_needed_translations()?);
+        let provided: HashSet<String> = HashSet::from_iter(trfile.provided_translations()?);
+        let mut inline = Suppressions::default();
+        inline.extend(inline_suppressions(language, trfile.lines()));
+
+        let mut found = Vec::new();
+        let missing: Vec<String> = self
+            .moddir
+            .required_translations()?
+            .into_iter()
+            .filter(|key| {
+                !provided.contains(key)
+                    && !inline.allows(ProblemKind::Missing, language, key)
+                    && !self
+                        .moddir
+                        .is_suppressed(ProblemKind::Missing, language, key)
+            })
+            .collect();
+        if !missing.is_empty() {
+            found.push(diagnostic(
+                Range::default(),
+                DiagnosticSeverity::WARNING,
+                format!(
+                    "{} requested keys have no translation here: {}",
+                    missing.len(),
+                    missing.join(", ")
+                ),
+            ));
+        }
+
+        for key in confirmed_unused(&mut self.moddir, &requested, &mut trfile)? {
+            let line = trfile.line_for(&key).unwrap_or(1) as u32 - 1;
+            found.push(diagnostic(
+                Range::new(Position::new(line, 0), Position::new(line, utf16_len(&key))),
+                DiagnosticSeverity::WARNING,
+                format!("{key} is not requested by any json file"),
+            ));
+        }
+        Ok(found)
+    }
+
+    /// Show a key's text in every language.
+    fn hover(&mut self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let Some(key) = self.key_at(&position.text_document.uri, position.position) else {
+            return Ok(None);
+        };
+        let mut lines = vec![format!("**{key}**"), String::new()];
+        for (language, mut trfile) in sorted_translations(&mut self.moddir)? {
+            let value = trfile
+                .translations()?
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| "*missing*".to_string());
+            lines.push(format!("- {language}: {value}"));
+        }
+        if self.moddir.builtin_keys().contains(&key) {
+            lines.push(String::new());
+            lines.push("Provided by SkyUI or another framework.".to_string());
+        }
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: lines.join("\n"),
+            }),
+            range: None,
+        }))
+    }
+
+    /// Find a key's line in every translation file, source language first.
+    fn definition(
+        &mut self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let Some(key) = self.key_at(&position.text_document.uri, position.position) else {
+            return Ok(None);
+        };
+        let source_language = self.moddir.config().source_language.clone();
+        let mut trfiles = sorted_translations(&mut self.moddir)?;
+        trfiles.sort_by_key(|(language, _trfile)| *language != source_language);
+
+        let mut locations = Vec::new();
+        for (_language, mut trfile) in trfiles {
+            trfile.translations()?;
+            let Some(line) = trfile.line_for(&key) else {
+                continue;
+            };
+            let Ok(uri) = Url::from_file_path(trfile.path()) else {
+                continue;
+            };
+            let line = line as u32 - 1;
+            locations.push(Location::new(
+                uri,
+                Range::new(Position::new(line, 0), Position::new(line, utf16_len(&key))),
+            ));
+        }
+        if locations.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(GotoDefinitionResponse::Array(locations)))
+    }
+
+    /// Offer every key the mod requests or its source language provides.
+    fn completion(&mut self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let Some(text) = self.documents.get(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(line) = text.lines().nth(position.position.line as usize) else {
+            return Ok(None);
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let cursor = char_index(line, position.position.character).min(chars.len());
+        let mut start = cursor;
+        while start > 0 && is_key_char(chars[start - 1]) && chars[start - 1] != '$' {
+            start -= 1;
+        }
+        if start == 0 || chars[start - 1] != '$' {
+            return Ok(None);
+        }
+        start -= 1;
+        let range = Range::new(
+            Position::new(
+                position.position.line,
+                utf16_len(&String::from_iter(&chars[..start])),
+            ),
+            position.position,
+        );
+
+        let source_language = self.moddir.config().source_language.clone();
+        let source = match self.moddir.translation_file_for(source_language.as_str())? {
+            Some(mut trfile) => trfile.translations()?,
+            None => HashMap::new(),
+        };
+        let mut keys: Vec<String> = self.moddir.all_needed_translations()?;
+        keys.extend(source.keys().cloned());
+        keys.sort();
+        keys.dedup();
+
+        let items = keys
+            .into_iter()
+            .map(|key| CompletionItem {
+                label: key.clone(),
+                kind: Some(CompletionItemKind::CONSTANT),
+                detail: source.get(&key).cloned(),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, key))),
+                ..Default::default()
+            })
+            .collect();
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    /// The translation key under the cursor in an open document, if there is one.
+    fn key_at(&self, uri: &Url, position: Position) -> Option<String> {
+        let text = self.documents.get(uri)?;
+        let line = text.lines().nth(position.line as usize)?;
+        key_in_line(line, position.character)
+    }
+}
+
+/// The translation key under a utf-16 column in a line, if there is one.
+fn key_in_line(line: &str, column: u32) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let cursor = char_index(line, column).min(chars.len());
+    let mut word_start = cursor;
+    while word_start > 0 && is_key_char(chars[word_start - 1]) {
+        word_start -= 1;
+    }
+    // Keys can run together, so a key starts at the last $ at or before the
+    // cursor and ends at the next $ or the end of the word.
+    let start = if chars.get(cursor) == Some(&'$') {
+        cursor
+    } else {
+        word_start + chars[word_start..cursor].iter().rposition(|c| *c == '$')?
+    };
+    let mut end = start + 1;
+    while end < chars.len() && is_key_char(chars[end]) && chars[end] != '$' {
+        end += 1;
+    }
+    if end - start < 2 {
+        return None;
+    }
+    Some(chars[start..end].iter().collect())
+}
+
+/// This mod's translation files, sorted by language.
+fn sorted_translations(moddir: &mut ModDirectory) -> Result<Vec<(String, Translation)>> {
+    let mut trfiles: Vec<(String, Translation)> = moddir.translation_files()?.into_iter().collect();
+    trfiles.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(trfiles)
+}
+
+fn extract<P: serde::de::DeserializeOwned>(request: Request) -> Result<P> {
+    Ok(serde_json::from_value(request.params)?)
+}
+
+fn diagnostic(range: Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("mcm-meta-helper".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+fn file_path(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+fn is_key_char(c: char) -> bool {
+    !c.is_whitespace() && !"\"'{}[](),:;<>".contains(c)
+}
+
+/// LSP counts characters in utf-16 code units.
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// The index of the character at a utf-16 column in a line.
+fn char_index(line: &str, column: u32) -> usize {
+    let mut units = 0;
+    for (idx, c) in line.chars().enumerate() {
+        if units >= column {
+            return idx;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.chars().count()
+}
+
+/// The LSP position of a byte offset in a document.
+fn position_at(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() as u32;
+    let line_start = before.rfind('\n').map(|xs| xs + 1).unwrap_or(0);
+    Position::new(line, utf16_len(&before[line_start..]))
+}
+
+/// Find the byte offset of the value a json pointer names, walking the text
+/// itself so we know where things are.
+fn locate_pointer(text: &str, path: &[String]) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut pos = skip_whitespace(bytes, 0);
+    for segment in path {
+        match bytes.get(pos)? {
+            b'{' => {
+                pos = skip_whitespace(bytes, pos + 1);
+                loop {
+                    let (key, after) = read_string(text, pos)?;
+                    pos = skip_whitespace(bytes, after);
+                    if *bytes.get(pos)? != b':' {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                    if key == *segment {
+                        break;
+                    }
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if *bytes.get(pos)? != b',' {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                }
+            }
+            b'[' => {
+                let index: usize = segment.parse().ok()?;
+                pos = skip_whitespace(bytes, pos + 1);
+                for _ in 0..index {
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if *bytes.get(pos)? != b',' {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(pos)
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// The offset just past the end of the string starting at this offset.
+fn string_end(bytes: &[u8], pos: usize) -> Option<usize> {
+    if *bytes.get(pos)? != b'"' {
+        return None;
+    }
+    let mut idx = pos + 1;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'"' => return Some(idx + 1),
+            _ => idx += 1,
+        }
+    }
+    None
+}
+
+fn read_string(text: &str, pos: usize) -> Option<(String, usize)> {
+    let end = string_end(text.as_bytes(), pos)?;
+    let value: String = serde_json::from_str(&text[pos..end]).ok()?;
+    Some((value, end))
+}
+
+/// The offset just past the end of the value starting at this offset.
+fn skip_value(bytes: &[u8], pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => string_end(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut idx = pos;
+            while idx < bytes.len() {
+                match bytes[idx] {
+                    b'"' => {
+                        idx = string_end(bytes, idx)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(idx + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                idx += 1;
+            }
+            None
+        }
+        _ => {
+            let mut idx = pos;
+            while idx < bytes.len() && !b",}] \t\r\n".contains(&bytes[idx]) {
+                idx += 1;
+            }
+            Some(idx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn key_in_line_finds_the_key_under_the_cursor() {
+        let line = "\"text\": \"$MyMod_Title\",";
+        assert_eq!(key_in_line(line, 12).as_deref(), Some("$MyMod_Title"));
+        assert_eq!(key_in_line(line, 2), None);
+    }
+
+    #[test]
+    fn key_in_line_handles_non_ascii_lines() {
+        let line = "$MyMod_Größe\tGröße ändern";
+        assert_eq!(key_in_line(line, 11).as_deref(), Some("$MyMod_Größe"));
+        assert_eq!(key_in_line(line, 15), None);
+
+        // Characters outside the basic plane take two utf-16 units.
+        let line = "\"🙂 $MyMod_Emoji\"";
+        assert_eq!(key_in_line(line, 8).as_deref(), Some("$MyMod_Emoji"));
+    }
+
+    #[test]
+    fn key_in_line_separates_adjacent_keys() {
+        assert_eq!(key_in_line("$One$Two", 2).as_deref(), Some("$One"));
+        assert_eq!(key_in_line("$One$Two", 4).as_deref(), Some("$Two"));
+        assert_eq!(key_in_line("$One$Two", 6).as_deref(), Some("$Two"));
+        assert_eq!(key_in_line("$One$Two", 8).as_deref(), Some("$Two"));
+    }
+
+    #[test]
+    fn locate_pointer_walks_nested_objects_and_arrays() {
+        let text = r#"{ "modName": "MyMod", "pages": [ { "a": 1 }, { "content": [ "x", { "text": "$Key" } ] } ] }"#;
+        let path = |xs: &[&str]| xs.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+
+        let at = locate_pointer(text, &path(&["pages", "1", "content", "1", "text"])).unwrap();
+        assert!(text[at..].starts_with("\"$Key\""));
+        let at = locate_pointer(text, &path(&["pages", "0"])).unwrap();
+        assert!(text[at..].starts_with("{ \"a\": 1 }"));
+        let at = locate_pointer(text, &path(&["modName"])).unwrap();
+        assert!(text[at..].starts_with("\"MyMod\""));
+        assert_eq!(locate_pointer(text, &path(&["pages", "2"])), None);
+        assert_eq!(locate_pointer(text, &path(&["nothing"])), None);
+    }
+
+    /// A language server for a fixture mod, with one of its files open.
+    fn serve(fixture: &ModFixture, relative: &str) -> (Server, Url) {
+        let mut server = Server {
+            moddir: fixture.moddir(),
+            schema: mcm_schema().unwrap(),
+            documents: HashMap::new(),
+        };
+        let path = fixture.path().join(relative).canonicalize().unwrap();
+        let uri = Url::from_file_path(path).unwrap();
+        let text = std::fs::read_to_string(uri.to_file_path().unwrap()).unwrap_or_default();
+        let params = DidOpenTextDocumentParams {
+            text_document: lsp_types::TextDocumentItem::new(uri.clone(), "json".into(), 1, text),
+        };
+        server
+            .notice(Notification::new(
+                DidOpenTextDocument::METHOD.to_string(),
+                params,
+            ))
+            .unwrap();
+        (server, uri)
+    }
+
+    fn request<P: serde::Serialize>(
+        server: &mut Server,
+        method: &str,
+        params: P,
+    ) -> serde_json::Value {
+        let response = server.respond(Request::new(1.into(), method.to_string(), params));
+        assert!(response.error.is_none(), "{:?}", response.error);
+        response.result.unwrap()
+    }
+
+    fn at(uri: &Url, line: u32, character: u32) -> lsp_types::TextDocumentPositionParams {
+        lsp_types::TextDocumentPositionParams::new(
+            lsp_types::TextDocumentIdentifier::new(uri.clone()),
+            Position::new(line, character),
+        )
+    }
+
+    fn fixture() -> ModFixture {
+        ModFixture::new(&["$MyMod_Title", "$MyMod_Help"])
+            .translation(
+                "MyMod_english.txt",
+                &["$MyMod_Title\tTitle", "$MyMod_Help\tHelp"],
+            )
+            .translation(
+                "MyMod_german.txt",
+                &["$MyMod_Title\tTitel", "$MyMod_Old\tAlt"],
+            )
+    }
+
+    const CONFIG: &str = "data/mcm/config/MyMod/config.json";
+
+    #[test]
+    fn diagnostics_mark_keys_some_language_lacks() {
+        let fixture = fixture();
+        let (mut server, uri) = serve(&fixture, CONFIG);
+        let warnings: Vec<Diagnostic> = server
+            .diagnostics(&uri)
+            .unwrap()
+            .into_iter()
+            .filter(|xs| xs.severity == Some(DiagnosticSeverity::WARNING))
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message,
+            "$MyMod_Help has no translation in german"
+        );
+        assert_eq!(warnings[0].range.start.line, 5);
+    }
+
+    #[test]
+    fn diagnostics_mark_missing_and_unused_keys_in_translation_files() {
+        let fixture = fixture();
+        let (mut server, uri) = serve(&fixture, "data/Interface/Translations/MyMod_german.txt");
+        let found = server.diagnostics(&uri).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            found[0].message,
+            "1 requested keys have no translation here: $MyMod_Help"
+        );
+        assert_eq!(
+            found[1].message,
+            "$MyMod_Old is not requested by any json file"
+        );
+        assert_eq!(found[1].range.start.line, 1);
+    }
+
+    #[test]
+    fn hover_shows_a_key_in_every_language() {
+        let fixture = fixture();
+        let (mut server, uri) = serve(&fixture, CONFIG);
+        let params = HoverParams {
+            text_document_position_params: at(&uri, 5, 20),
+            work_done_progress_params: Default::default(),
+        };
+        let hover: Hover =
+            serde_json::from_value(request(&mut server, HoverRequest::METHOD, params)).unwrap();
+        let HoverContents::Markup(contents) = hover.contents else {
+            panic!("hover should be markdown");
+        };
+        assert_eq!(
+            contents.value,
+            "**$MyMod_Help**\n\n- english: Help\n- german: *missing*"
+        );
+    }
+
+    #[test]
+    fn definition_finds_a_key_in_each_translation_file() {
+        let fixture = fixture();
+        let (mut server, uri) = serve(&fixture, CONFIG);
+        let params = GotoDefinitionParams {
+            text_document_position_params: at(&uri, 4, 20),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let found: GotoDefinitionResponse =
+            serde_json::from_value(request(&mut server, GotoDefinition::METHOD, params)).unwrap();
+        let GotoDefinitionResponse::Array(locations) = found else {
+            panic!("definitions should be a list");
+        };
+        let files: Vec<String> = locations
+            .iter()
+            .map(|xs| xs.uri.path().rsplit('/').next().unwrap().to_string())
+            .collect();
+        assert_eq!(files, ["MyMod_english.txt", "MyMod_german.txt"]);
+        assert!(locations.iter().all(|xs| xs.range.start.line == 0));
+    }
+
+    #[test]
+    fn completion_offers_keys_after_a_dollar_sign() {
+        let fixture = fixture();
+        let (mut server, uri) = serve(&fixture, CONFIG);
+        server
+            .documents
+            .insert(uri.clone(), "{ \"text\": \"$My".to_string());
+        let params = CompletionParams {
+            text_document_position: at(&uri, 0, 14),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let found: CompletionResponse =
+            serde_json::from_value(request(&mut server, Completion::METHOD, params)).unwrap();
+        let CompletionResponse::Array(items) = found else {
+            panic!("completions should be a list");
+        };
+        let labels: Vec<&str> = items.iter().map(|xs| xs.label.as_str()).collect();
+        assert_eq!(labels, ["$MyMod_Help", "$MyMod_Title"]);
+        assert_eq!(items[1].detail.as_deref(), Some("Title"));
+        let Some(CompletionTextEdit::Edit(ref edit)) = items[0].text_edit else {
+            panic!("completions should replace what was typed");
+        };
+        assert_eq!(
+            edit.range,
+            Range::new(Position::new(0, 11), Position::new(0, 14))
+        );
+    }
+}
@@ -7,18 +7,56 @@ use jsonschema::JSONSchema;
 use owo_colors::OwoColorize;
 
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::path::PathBuf;
 
+mod collisions;
+pub use collisions::*;
+mod config;
+pub use config::*;
+mod editor;
+pub use editor::*;
+mod exchange;
+pub use exchange::*;
+mod lint;
+pub use lint::*;
+mod lsp;
+pub use lsp::*;
 mod moddir;
 pub use moddir::*;
 mod translation;
 pub use translation::*;
+mod watch;
+pub use watch::*;
+mod xliff;
+pub use xliff::*;
 mod skyui_translations;
 pub use skyui_translations::*;
+mod spreadsheet;
+pub use spreadsheet::*;
+mod stats;
+pub use stats::*;
+mod naming;
+pub use naming::*;
+mod po;
+pub use po::*;
+mod prune;
+pub use prune::*;
+mod rename;
+pub use rename::*;
+mod results;
+pub use results::*;
+mod suppressions;
+pub use suppressions::*;
+mod filenames;
+pub use filenames::*;
+mod format;
+pub use format::*;
 mod formatting;
 pub use formatting::*;
+#[cfg(test)]
+mod fixture;
 
 /// Help manage MCM Helper translation files by checking for missing or unused translations.
 ///
@@ -44,37 +82,199 @@ pub struct Args {
 #[derive(Clone, Debug, Subcommand)]
 pub enum Command {
     /// Cross-check required translation strings versus the ones found in translation files.
-    Check {
-        /// Limit the check to a single language file.
-        language: String,
-    },
+    Check(CheckOptions),
     /// Copy translations from the source language file to any language file missing translations.
     Copy {
-        /// The language to use as a source. E.g., `english`, `japanese`.
-        language: String,
+        /// The language to use as a source. E.g., `english`, `japanese`. Defaults to the
+        /// source language in the project config, or `english`.
+        language: Option<String>,
     },
     /// Update all translation files with missing translation strings and placeholders.
-    Update,
+    Update {
+        /// Create a file for each required language that doesn't have one, seeded
+        /// with the source language's translations.
+        #[clap(long)]
+        create_missing: bool,
+    },
+    /// Remove duplicated keys from translation files, keeping one appearance of each.
+    Dedupe {
+        /// The language file to fix, or `all` for every language.
+        language: String,
+        /// Which appearance of a duplicated key to keep.
+        #[clap(long, value_enum, default_value = "first")]
+        keep: KeepOccurrence,
+    },
+    /// Rewrite translation files in a canonical layout.
+    Format {
+        /// The language file to format, or `all` for every language.
+        #[clap(default_value = "all")]
+        language: String,
+        /// Mirror the source language file's order, or group translations by config page.
+        #[clap(long, value_enum, default_value = "source")]
+        order: FormatOrder,
+        /// Don't write anything; fail if any file isn't already formatted.
+        #[clap(long)]
+        check: bool,
+    },
+    /// Remove unused translations from every translation file, backing up each file changed.
+    Prune {
+        /// List what would be removed without changing anything.
+        #[clap(long)]
+        dry_run: bool,
+        /// Don't ask for confirmation before removing anything.
+        #[clap(long, short)]
+        yes: bool,
+    },
+    /// Rename a translation key in every json, source, and translation file that uses it.
+    RenameKey {
+        /// The key's current name, including its leading $.
+        old: String,
+        /// The key's new name, including its leading $.
+        new: String,
+        /// List the files that would change without changing anything.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Rename every key that breaks the naming convention in mcm-meta-helper.toml.
+    FixNames {
+        /// List the files that would change without changing anything.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Report keys this mod defines that other installed mods define with different text.
+    Collisions {
+        /// A game Data folder, or a mod manager's folder of installed mods.
+        path: PathBuf,
+    },
+    /// Print translation completion statistics for every language.
+    Stats {
+        /// Print the statistics as json, e.g. for generating progress badges.
+        #[clap(long)]
+        json: bool,
+    },
     /// Validate the mcm config json file against the MCM helper schema
-    Validate,
+    Validate {
+        /// Keep running, and validate again whenever a file in the mod changes.
+        #[clap(long)]
+        watch: bool,
+    },
+    /// Edit translations in a table of keys and languages, in the terminal.
+    Edit {
+        /// Languages to show next to the source language. Shows every language if none are given.
+        languages: Vec<String>,
+    },
+    /// Export every requested key and its translations for translators' tools.
+    Export {
+        /// The format to export to.
+        #[clap(long, value_enum)]
+        format: ExchangeFormat,
+        /// The directory to write the exported files to.
+        #[clap(long, short, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Merge translations from exported files back into the translation files.
+    Import {
+        /// The translated files to import.
+        #[clap(required = true)]
+        files: Vec<PathBuf>,
+        /// Report what would change without changing anything.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Run a language server over stdio, for editor diagnostics, hover, and
+    /// completion of translation keys.
+    Lsp,
 }
 
-fn check(args: &Args, language: &String) -> Result<bool, Report> {
-    let check_all = *language == "all";
+/// Options for the check command.
+#[derive(Clone, Debug, clap::Args)]
+pub struct CheckOptions {
+    /// Limit the check to a single language file.
+    language: String,
+    /// Add every problem found to the suppressions file, so future checks
+    /// report only new problems.
+    #[clap(long)]
+    write_suppressions: bool,
+    /// Record the missing and unused keys found in this json file, for use with `--against`.
+    #[clap(long, value_name = "FILE", conflicts_with = "against")]
+    baseline: Option<PathBuf>,
+    /// Compare against a baseline json file, failing only if new problems appear.
+    #[clap(long, value_name = "FILE")]
+    against: Option<PathBuf>,
+    /// Fix malformed lines that can be repaired safely, like entries separated by
+    /// spaces instead of a tab, before checking.
+    #[clap(long)]
+    fix: bool,
+    /// Keep running, and check again whenever a file in the mod changes.
+    #[clap(long, conflicts_with_all = ["write_suppressions", "baseline"])]
+    watch: bool,
+}
 
+fn check(args: &Args, opts: &CheckOptions) -> Result<bool, Report> {
+    if opts.watch {
+        return watch(args, |moddir| check_mod(args, opts, moddir));
+    }
     let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    check_mod(args, opts, &mut moddir)
+}
+
+fn check_mod(args: &Args, opts: &CheckOptions, moddir: &mut ModDirectory) -> Result<bool, Report> {
+    let language = &opts.language;
+    let write_suppressions = opts.write_suppressions;
+    let check_all = *language == "all";
 
     let requested = moddir
         .all_needed_translations()
         .context("Finding all requested translations")?;
     let requested_set: HashSet<String> =
         HashSet::from_iter(requested.iter().map(|xs| xs.to_owned()));
+    let required_set: HashSet<String> = HashSet::from_iter(moddir.required_translations()?);
 
     let mut trfiles = moddir.translation_files()?;
     let mut errors_caught: Vec<Report> = Vec::new();
+    let mut problems_found: Vec<Suppression> = Vec::new();
+    let mut results = CheckResults::default();
+    let missing_required = moddir.missing_languages()?;
+    let misnamed: Vec<FilenameProblem> = misnamed_translation_files(moddir)?
+        .into_iter()
+        .filter(|xs| check_all || xs.language.as_ref() == Some(language))
+        .collect();
+
+    let naming = match NamingRule::for_mod(moddir)? {
+        Some(rule) => {
+            let mut keys = requested_set.clone();
+            for (trlanguage, trfile) in trfiles.iter_mut() {
+                if check_all || trlanguage == language {
+                    keys.extend(trfile.provided_translations()?);
+                }
+            }
+            naming_problems(&rule, &keys, moddir.builtin_keys())
+        }
+        None => Vec::new(),
+    };
+
+    let locations = moddir.requested_key_locations()?;
+    let builtin = moddir.builtin_keys().clone();
+    let override_severity = moddir.config().builtin_overrides;
+    let source_language = moddir.config().source_language.clone();
+    let source_values = match moddir.translation_file_for(source_language.as_str())? {
+        Some(mut source) => source.translations()?,
+        None => HashMap::new(),
+    };
 
     let mut report_for =
         |table: &mut Table, language: &str, trfile: &mut Translation| -> Result<bool> {
+            if opts.fix {
+                let fixed = trfile.fix_malformed_lines()?;
+                if fixed > 0 {
+                    trfile.write()?;
+                    log::info!(
+                        "{}: fixed {} malformed lines",
+                        trfile.display().bold().blue(),
+                        fixed
+                    );
+                }
+            }
             let provided = match trfile.provided_translations() {
                 Ok(v) => v,
                 Err(e) => {
@@ -89,27 +289,101 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
             };
             let provided_set: HashSet<String> =
                 HashSet::from_iter(provided.iter().map(|xs| xs.to_owned()));
+            let mut inline = Suppressions::default();
+            inline.extend(inline_suppressions(language, trfile.lines()));
+            let suppressed = |kind: ProblemKind, key: &String| {
+                inline.allows(kind, language, key) || moddir.is_suppressed(kind, language, key)
+            };
 
-            let winnowed = requested_set.difference(&SKYUI_KEYS);
-            let winnowed: HashSet<String> = winnowed.cloned().collect();
-            let missing = winnowed.difference(&provided_set);
-            let mut missing_tags: Vec<String> = missing.cloned().collect();
+            let missing = required_set.difference(&provided_set);
+            let mut missing_tags: Vec<String> = missing
+                .filter(|xs| !suppressed(ProblemKind::Missing, xs))
+                .cloned()
+                .collect();
             missing_tags.sort();
 
-            let unused = provided_set.difference(&requested_set);
-            let unused_tags: Vec<String> = unused.cloned().collect();
-            let mut unused_tags = moddir.ripgrep_search(unused_tags);
-            unused_tags.sort();
+            // Keys that differ only in case get their own report, not one missing and one unused.
+            let case_mismatched = case_mismatches(&missing_tags, &provided_set);
+            let miscased: HashSet<&String> = case_mismatched
+                .iter()
+                .flat_map(|xs| [&xs.requested, &xs.provided])
+                .collect();
+            missing_tags.retain(|xs| !miscased.contains(xs));
+
+            let mut unused_tags = confirmed_unused(moddir, &requested_set, trfile)?;
+
+            results.record(language, &missing_tags, &unused_tags);
+            if write_suppressions {
+                problems_found.extend(missing_tags.iter().map(|key| Suppression {
+                    kind: ProblemKind::Missing,
+                    language: Some(language.to_string()),
+                    key: key.clone(),
+                }));
+                problems_found.extend(unused_tags.iter().map(|key| Suppression {
+                    kind: ProblemKind::Unused,
+                    language: Some(language.to_string()),
+                    key: key.clone(),
+                }));
+            }
 
-            if missing_tags.is_empty() && unused_tags.is_empty() {
+            let translations = trfile.translations()?;
+            let mismatches = if language == source_language {
+                Vec::new()
+            } else {
+                placeholder_mismatches(&source_values, &translations)
+            };
+            let untranslated: Vec<String> =
+                untranslated_values(&source_values, &translations, language == source_language)
+                    .into_iter()
+                    .filter(|(key, _why)| required_set.contains(key))
+                    .map(|(key, _why)| key)
+                    .collect();
+            let complete =
+                completion_percent(required_set.len(), missing_tags.len(), untranslated.len());
+            // With a baseline, missing and unused keys are reported as changes from it instead.
+            if opts.against.is_some() {
+                missing_tags.clear();
+                unused_tags.clear();
+            }
+            let duplicates = trfile.duplicates()?;
+            let conflicting = duplicates.iter().any(|xs| xs.values_differ());
+            let malformed = trfile.malformed_lines()?;
+            let hiding = malformed.iter().any(|xs| xs.hides_translation);
+            // Redefining a built-in key changes its text for every other mod, too.
+            let mut overrides: Vec<String> = if override_severity == Severity::Off {
+                Vec::new()
+            } else {
+                provided_set.intersection(&builtin).cloned().collect()
+            };
+            overrides.sort();
+            let overrides_fail = override_severity == Severity::Error && !overrides.is_empty();
+
+            if missing_tags.is_empty()
+                && unused_tags.is_empty()
+                && mismatches.is_empty()
+                && untranslated.is_empty()
+                && duplicates.is_empty()
+                && malformed.is_empty()
+                && case_mismatched.is_empty()
+                && overrides.is_empty()
+            {
                 log::debug!("{}: no problems found", language.bold().blue());
                 return Ok(true);
             }
 
-            let count_cell = if missing_tags.len() == 1 {
-                Cell::new("1 missing translation found")
+            let count_cell = if opts.against.is_some() {
+                Cell::new(format!("{:.0}% complete", complete.floor()))
+            } else if missing_tags.len() == 1 {
+                Cell::new(format!(
+                    "1 missing translation found; {:.0}% complete",
+                    complete.floor()
+                ))
             } else {
-                Cell::new(format!("{} missing translations found", missing_tags.len()))
+                Cell::new(format!(
+                    "{} missing translations found; {:.0}% complete",
+                    missing_tags.len(),
+                    complete.floor()
+                ))
             };
             table.add_row(vec![
                 Cell::new(language)
@@ -119,7 +393,11 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
             ]);
 
             if args.quiet {
-                return Ok(missing_tags.is_empty());
+                return Ok(missing_tags.is_empty()
+                    && mismatches.is_empty()
+                    && !conflicting
+                    && !hiding
+                    && !overrides_fail);
             }
 
             #[allow(clippy::comparison_chain)]
@@ -166,8 +444,115 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
                 }
             }
 
-            // We do not fail tests if we have unused translations.
-            Ok(missing_tags.is_empty())
+            if !overrides.is_empty() {
+                let color = if overrides_fail {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                };
+                table.add_row(vec![
+                    Cell::new("built-in")
+                        .fg(color)
+                        .add_attribute(Attribute::Bold),
+                    Cell::new(format!(
+                        "redefines keys SkyUI or another framework provides for every mod\n{}",
+                        grid_string(&overrides, 20).trim()
+                    ))
+                    .set_delimiter('\n'),
+                ]);
+            }
+
+            if !untranslated.is_empty() {
+                let summary = if untranslated.len() == 1 {
+                    "1 value stubbed or identical to the source language".to_string()
+                } else {
+                    format!(
+                        "{} values stubbed or identical to the source language",
+                        untranslated.len()
+                    )
+                };
+                let report_cell = if args.verbose {
+                    let gridded = grid_string(&untranslated, 20);
+                    Cell::new(format!("{summary}\n{}", gridded.trim())).set_delimiter('\n')
+                } else {
+                    Cell::new(summary)
+                };
+                table.add_row(vec![
+                    Cell::new("untranslated")
+                        .fg(Color::Yellow)
+                        .add_attribute(Attribute::Bold),
+                    report_cell,
+                ]);
+            }
+
+            for mismatch in case_mismatched.iter() {
+                let requested_in: Vec<String> = locations
+                    .get(&mismatch.requested)
+                    .map(|files| files.iter().map(|f| moddir.relative_display(f)).collect())
+                    .unwrap_or_default();
+                let provided_at = trfile
+                    .line_for(&mismatch.provided)
+                    .map(|line| format!("{} line {line}", trfile.display()))
+                    .unwrap_or_else(|| trfile.display().to_string());
+                table.add_row(vec![
+                    Cell::new("case mismatch")
+                        .fg(Color::Yellow)
+                        .add_attribute(Attribute::Bold),
+                    Cell::new(format!(
+                        "{} in {}\n{} in {}",
+                        mismatch.requested,
+                        requested_in.join(", "),
+                        mismatch.provided,
+                        provided_at
+                    ))
+                    .set_delimiter('\n'),
+                ]);
+            }
+
+            for mismatch in mismatches.iter() {
+                table.add_row(vec![
+                    Cell::new("placeholders")
+                        .fg(Color::Red)
+                        .add_attribute(Attribute::Bold),
+                    Cell::new(mismatch.to_string()),
+                ]);
+            }
+
+            for duplicate in duplicates.iter() {
+                let color = if duplicate.values_differ() {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                };
+                table.add_row(vec![
+                    Cell::new("duplicate")
+                        .fg(color)
+                        .add_attribute(Attribute::Bold),
+                    Cell::new(duplicate.to_string()).set_delimiter('\n'),
+                ]);
+            }
+
+            for problem in malformed.iter() {
+                let color = if problem.hides_translation {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                };
+                table.add_row(vec![
+                    Cell::new("malformed")
+                        .fg(color)
+                        .add_attribute(Attribute::Bold),
+                    Cell::new(problem.to_string()).set_delimiter('\n'),
+                ]);
+            }
+
+            // We do not fail tests if we have unused translations, duplicates that agree,
+            // or stray lines the game skips. Redefined built-in keys fail only if configured to.
+            Ok(missing_tags.is_empty()
+                && mismatches.is_empty()
+                && !conflicting
+                && !hiding
+                && !overrides_fail)
         };
 
     let mut table = Table::new();
@@ -175,8 +560,47 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
         .load_preset(NOTHING)
         .set_content_arrangement(ContentArrangement::Dynamic);
 
-    let mut checks_passed = true;
+    let mut checks_passed = misnamed.is_empty();
+    for problem in misnamed.iter() {
+        table.add_row(vec![
+            Cell::new("filename")
+                .fg(Color::Red)
+                .add_attribute(Attribute::Bold),
+            Cell::new(format!("{}: {}", problem.filename, problem.problem)),
+        ]);
+    }
+    if !misnamed.is_empty() && !args.quiet {
+        table.add_row(vec![Cell::new("---"), Cell::new("")]);
+    }
+
+    checks_passed &= naming.is_empty();
+    for problem in naming.iter() {
+        table.add_row(vec![
+            Cell::new("naming")
+                .fg(Color::Red)
+                .add_attribute(Attribute::Bold),
+            Cell::new(problem.to_string()),
+        ]);
+    }
+    if !naming.is_empty() && !args.quiet {
+        table.add_row(vec![Cell::new("---"), Cell::new("")]);
+    }
+
     if check_all {
+        for required in missing_required.iter() {
+            table.add_row(vec![
+                Cell::new(required)
+                    .fg(Color::Blue)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("no translation file found")
+                    .fg(Color::Red)
+                    .add_attribute(Attribute::Bold),
+            ]);
+            if !args.quiet {
+                table.add_row(vec![Cell::new("---"), Cell::new("")]);
+            }
+            checks_passed = false;
+        }
         for (language, mut trfile) in trfiles {
             checks_passed &= report_for(&mut table, language.as_str(), &mut trfile)?;
             if !args.quiet {
@@ -190,9 +614,35 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
                 language.bold().yellow()
             )
         });
-        checks_passed = report_for(&mut table, language.as_str(), trfile)?;
+        checks_passed &= report_for(&mut table, language.as_str(), trfile)?;
     }
     log::warn!("{}", table);
+    if let Some(ref baseline) = opts.against {
+        let baseline = CheckResults::load(baseline)?;
+        checks_passed &= report_against(args, &results.diff(&baseline));
+    }
+
+    if let Some(ref baseline) = opts.baseline {
+        results.write(baseline)?;
+        log::info!(
+            "Recorded the problems found as a baseline in {}",
+            baseline.display().blue()
+        );
+    }
+
+    if write_suppressions {
+        let fpath = moddir.suppressions_path();
+        let mut suppressions = moddir.suppressions().clone();
+        let before = suppressions.len();
+        suppressions.extend(problems_found);
+        suppressions.write(&fpath)?;
+        log::info!(
+            "Added {} suppressions to {}; future checks will report only new problems.",
+            (suppressions.len() - before).bold(),
+            fpath.display().blue()
+        );
+        checks_passed = true;
+    }
 
     if !errors_caught.is_empty() {
         Err(errors_caught.pop().unwrap())
@@ -201,16 +651,66 @@ fn check(args: &Args, language: &String) -> Result<bool, Report> {
     }
 }
 
-fn copy(args: &Args, language: &String) -> Result<bool, Report> {
+/// Report how the problems found differ from a baseline. Returns false if any new
+/// missing translations appeared.
+fn report_against(args: &Args, diffs: &BTreeMap<String, LanguageDiff>) -> bool {
+    if diffs.is_empty() {
+        log::info!("No changes from the baseline.");
+        return true;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(NOTHING)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let mut regressions = 0;
+    for (language, diff) in diffs {
+        regressions += diff.new_missing.len();
+        table.add_row(vec![
+            Cell::new(language)
+                .fg(Color::Blue)
+                .add_attribute(Attribute::Bold),
+            Cell::new(format!(
+                "{} new problems, {} fixed",
+                diff.new_missing.len() + diff.new_unused.len(),
+                diff.fixed_missing.len() + diff.fixed_unused.len()
+            )),
+        ]);
+        let sections = [
+            ("new missing", Color::Red, &diff.new_missing),
+            ("new unused", Color::Yellow, &diff.new_unused),
+            ("fixed missing", Color::Green, &diff.fixed_missing),
+            ("fixed unused", Color::Green, &diff.fixed_unused),
+        ];
+        for (label, color, keys) in sections {
+            if keys.is_empty() || (args.quiet && color == Color::Green) {
+                continue;
+            }
+            table.add_row(vec![
+                Cell::new(label).fg(color).add_attribute(Attribute::Bold),
+                Cell::new(grid_string(keys, 20).trim()).set_delimiter('\n'),
+            ]);
+        }
+    }
+    log::warn!("{}", table);
+
+    regressions == 0
+}
+
+fn copy(args: &Args, language: &Option<String>) -> Result<bool, Report> {
     let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let language = language
+        .clone()
+        .unwrap_or_else(|| moddir.config().source_language.clone());
+    let language = &language;
     let trfiles = moddir.translation_files()?;
     let mut source = moddir
         .translation_file_for(language)?
         .expect("Translations file for {language} does not exist!");
     source.load_translations()?;
     let source_translations = source.translations()?;
-    let source_has: HashSet<String> =
-        HashSet::from_iter(source_translations.iter().map(|(k, _v)| k.to_owned()));
+    let source_has: Vec<String> = source_translations.keys().cloned().collect();
 
     log::info!(
         "\nCopying {} translation strings to other languages:",
@@ -223,12 +723,21 @@ fn copy(args: &Args, language: &String) -> Result<bool, Report> {
         }
 
         let target_trs = target.translations()?;
-        let target_has: HashSet<String> =
-            HashSet::from_iter(target_trs.iter().map(|(k, _v)| k.to_owned()));
+        let target_has: HashSet<String> = target_trs.keys().cloned().collect();
+        let missing: Vec<String> = source_has
+            .iter()
+            .filter(|xs| !target_has.contains(*xs))
+            .cloned()
+            .collect();
+        // Keys the target spells with different capitalization aren't missing.
+        let miscased: HashSet<String> = case_mismatches(&missing, &target_has)
+            .into_iter()
+            .map(|xs| xs.requested)
+            .collect();
 
         let mut count = 0;
         log::info!("{}...", target_lang.blue());
-        for missing in source_has.difference(&target_has) {
+        for missing in missing.iter().filter(|xs| !miscased.contains(*xs)) {
             log::debug!("    + {missing}");
             target.append_translation(
                 missing.clone(),
@@ -254,14 +763,74 @@ fn copy(args: &Args, language: &String) -> Result<bool, Report> {
     Ok(true)
 }
 
-fn update(args: &Args) -> Result<bool, Report> {
+/// Create a translation file for every required language that lacks one, as a
+/// copy of the source language's file.
+fn create_missing_languages(moddir: &mut ModDirectory) -> Result<(), Report> {
+    let missing = moddir.missing_languages()?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let source_language = moddir.config().source_language.clone();
+    let Some(mut source) = moddir.translation_file_for(source_language.as_str())? else {
+        return Err(eyre::eyre!(
+            "Can't create missing language files without a {} translation file to copy.",
+            source_language
+        ));
+    };
+    source.load_translations()?;
+    let plugin = match split_translation_filename(source.path()) {
+        Some((plugin, _language)) => plugin,
+        None => moddir.name().to_string(),
+    };
+
+    for language in missing {
+        let fpath = moddir
+            .translations_dir()
+            .join(format!("{plugin}_{language}.txt"));
+        let mut created = Translation::new(fpath, language.as_str());
+        created.set_lines(source.lines().clone());
+        created.write()?;
+        log::info!(
+            "{}: created from {}",
+            created.display().bold().blue(),
+            source.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn update(args: &Args, create_missing: bool) -> Result<bool, Report> {
     let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    if create_missing {
+        create_missing_languages(&mut moddir)?;
+    }
 
-    let requested = moddir
-        .all_needed_translations()
+    let required = moddir
+        .required_translations()
         .context("Finding all requested translations")?;
-    let requested_set: HashSet<String> =
-        HashSet::from_iter(requested.iter().map(|xs| xs.to_owned()));
+    let required_set: HashSet<String> = HashSet::from_iter(required);
+
+    // New stubs go next to their neighbours in the source language file, or in
+    // the json files if the source language doesn't have them either.
+    let config_order = moddir.requested_keys_in_order()?;
+    let source_language = moddir.config().source_language.clone();
+    let source_order = match moddir.translation_file_for(source_language.as_str())? {
+        Some(mut source) => {
+            source.load_translations()?;
+            source.ordered_keys()
+        }
+        None => Vec::new(),
+    };
+    let position = |key: &String| {
+        source_order
+            .iter()
+            .position(|xs| xs == key)
+            .unwrap_or_else(|| {
+                source_order.len() + config_order.iter().position(|xs| xs == key).unwrap_or(0)
+            })
+    };
 
     let trfiles = moddir.translation_files()?;
     let padding = trfiles.iter().fold(30, |acc, (_lang, trfile)| {
@@ -274,16 +843,30 @@ fn update(args: &Args) -> Result<bool, Report> {
         let provided_set: HashSet<String> =
             HashSet::from_iter(provided.iter().map(|xs| xs.to_owned()));
 
-        let winnowed = requested_set.difference(&SKYUI_KEYS);
-        let winnowed: HashSet<String> = winnowed.cloned().collect();
-        let missing = winnowed.difference(&provided_set);
+        let missing: Vec<String> = required_set.difference(&provided_set).cloned().collect();
+        // A key provided with different capitalization is a case mismatch for
+        // `check` to report; a stub would only add a second spelling.
+        let miscased: HashSet<String> = case_mismatches(&missing, &provided_set)
+            .into_iter()
+            .map(|xs| xs.requested)
+            .collect();
 
-        let mut mvec: Vec<&String> = missing.collect();
+        let mut mvec: Vec<&String> = missing
+            .iter()
+            .filter(|xs| !miscased.contains(*xs))
+            .collect();
         if mvec.is_empty() {
             log::debug!("{:>padding$}: none needed", trfile.display().bold().blue());
         } else {
-            mvec.sort();
-            trfile.add_stub_translation(mvec.as_slice())?;
+            mvec.sort_by_key(|key| (position(key), *key));
+            for key in mvec.iter() {
+                trfile.insert_in_order(
+                    key.to_string(),
+                    stub_value(key),
+                    &[source_order.as_slice(), config_order.as_slice()],
+                );
+            }
+            trfile.write()?;
             let prefix = if mvec.len() == 1 {
                 "1 stub".to_string()
             } else {
@@ -300,9 +883,129 @@ fn update(args: &Args) -> Result<bool, Report> {
     Ok(true)
 }
 
-fn validate_config(args: &Args) -> Result<bool, Report> {
-    // from moddir, read ./mcm/config/**/config.json
+fn dedupe(args: &Args, language: &String, keep: KeepOccurrence) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let trfiles = moddir.translation_files()?;
+    if *language != "all" && !trfiles.contains_key(language) {
+        return Err(eyre::eyre!(
+            "Can't find a translation file for language {}",
+            language.bold().yellow()
+        ));
+    }
+
+    for (lang, mut trfile) in trfiles {
+        if *language != "all" && lang != *language {
+            continue;
+        }
+        let removed = trfile.dedupe(keep)?;
+        if removed == 0 {
+            log::debug!("{}: no duplicates", trfile.display().bold().blue());
+            continue;
+        }
+        trfile.write()?;
+        let prefix = if removed == 1 {
+            "1 duplicate line".to_string()
+        } else {
+            format!("{removed} duplicate lines")
+        };
+        log::info!("{}: {} removed", trfile.display().bold().blue(), prefix);
+    }
+
+    Ok(true)
+}
+
+fn format(
+    args: &Args,
+    language: &String,
+    order: FormatOrder,
+    check_only: bool,
+) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let source_language = moddir.config().source_language.clone();
+    let Some(mut source) = moddir.translation_file_for(source_language.as_str())? else {
+        return Err(eyre::eyre!(
+            "Can't format translation files without a {} translation file.",
+            source_language
+        ));
+    };
+    source.load_translations()?;
+
+    let layout = match order {
+        FormatOrder::Source => layout_from_source(&source),
+        FormatOrder::Pages => {
+            let source_values = source.translations()?;
+            let sections: Vec<(String, Vec<String>)> = moddir
+                .config_sections()?
+                .into_iter()
+                .map(|(title, keys)| {
+                    let title = source_values.get(&title).cloned().unwrap_or(title);
+                    (title, keys)
+                })
+                .collect();
+            layout_from_sections(&sections)
+        }
+    };
+
+    let trfiles = moddir.translation_files()?;
+    if *language != "all" && !trfiles.contains_key(language) {
+        return Err(eyre::eyre!(
+            "Can't find a translation file for language {}",
+            language.bold().yellow()
+        ));
+    }
+
+    let mut all_formatted = true;
+    for (lang, mut trfile) in trfiles {
+        if *language != "all" && lang != *language {
+            continue;
+        }
+        if trfile.malformed_lines()?.iter().any(|xs| xs.hides_translation) {
+            log::warn!(
+                "{}: skipped because it has malformed lines; run `check {lang}` to see them.",
+                trfile.display().bold().yellow()
+            );
+            all_formatted = false;
+            continue;
+        }
+
+        let formatted = formatted_lines(&trfile, &layout);
+        if formatted == *trfile.lines() && trfile.final_newline() {
+            log::debug!("{}: already formatted", trfile.display().bold().blue());
+            continue;
+        }
+        if check_only {
+            log::warn!("{}: needs formatting", trfile.display().bold().red());
+            all_formatted = false;
+        } else {
+            trfile.set_lines(formatted);
+            trfile.set_final_newline(true);
+            trfile.write()?;
+            log::info!("{}: formatted", trfile.display().bold().blue());
+        }
+    }
+
+    Ok(all_formatted)
+}
+
+fn validate_config(args: &Args, watch_files: bool) -> Result<bool, Report> {
+    if watch_files {
+        return watch(args, validate_mod);
+    }
     let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    validate_mod(&mut moddir)
+}
+
+/// The MCM Helper config.json schema, compiled.
+fn mcm_schema() -> Result<JSONSchema, Report> {
+    let schema_json: serde_json::Value =
+        serde_json::from_str(include_str!("../schemas/config.schema.json"))?;
+    let schema = JSONSchema::compile(&schema_json)
+        .expect("the default MCM Helper schema should be valid json!");
+    Ok(schema)
+}
+
+fn validate_mod(moddir: &mut ModDirectory) -> Result<bool, Report> {
+    // from moddir, read ./mcm/config/**/config.json
     let Some(fpath) = moddir.find_config()? else {
         log::info!(
             "No MCM Helper {} files found to check.",
@@ -323,10 +1026,7 @@ fn validate_config(args: &Args) -> Result<bool, Report> {
         .collect();
     let display_name = lastbits.display().to_string();
 
-    let schema_json: serde_json::Value =
-        serde_json::from_str(include_str!("../schemas/config.schema.json"))?;
-    let schema = JSONSchema::compile(&schema_json)
-        .expect("the default MCM Helper schema should be valid json!");
+    let schema = mcm_schema()?;
 
     let file = File::open(&fpath)?;
     let rdr = std::io::BufReader::new(file);
@@ -369,19 +1069,48 @@ fn main() -> Result<(), Report> {
         log::Level::Info
     };
 
-    loggerv::Logger::new()
+    // The language server talks to the editor over stdout, so everything it
+    // logs goes to stderr, where editors show it as server output.
+    let serving = matches!(args.cmd, Command::Lsp);
+    let mut logger = loggerv::Logger::new()
         .max_level(level)
         .line_numbers(false)
         .module_path(false)
-        .colors(true)
-        .init()
-        .unwrap();
+        .colors(!serving);
+    if serving {
+        for level in [log::Level::Info, log::Level::Debug, log::Level::Trace] {
+            logger = logger.output(&level, loggerv::Output::Stderr);
+        }
+    }
+    logger.init().unwrap();
 
     let result = match args.cmd {
-        Command::Check { ref language } => check(&args, language),
+        Command::Check(ref opts) => check(&args, opts),
         Command::Copy { ref language } => copy(&args, language),
-        Command::Update => update(&args),
-        Command::Validate => validate_config(&args),
+        Command::Update { create_missing } => update(&args, create_missing),
+        Command::Dedupe { ref language, keep } => dedupe(&args, language, keep),
+        Command::Format {
+            ref language,
+            order,
+            check,
+        } => format(&args, language, order, check),
+        Command::Prune { dry_run, yes } => prune(&args, dry_run, yes),
+        Command::RenameKey {
+            ref old,
+            ref new,
+            dry_run,
+        } => rename_key(&args, old, new, dry_run),
+        Command::FixNames { dry_run } => fix_names(&args, dry_run),
+        Command::Collisions { ref path } => collisions(&args, path),
+        Command::Stats { json } => stats(&args, json),
+        Command::Validate { watch } => validate_config(&args, watch),
+        Command::Edit { ref languages } => edit(&args, languages),
+        Command::Export { format, ref output } => export(&args, format, output),
+        Command::Import {
+            ref files,
+            dry_run,
+        } => import(&args, files, dry_run),
+        Command::Lsp => lsp(&args),
     };
 
     match result {
@@ -400,3 +1129,161 @@ fn main() -> Result<(), Report> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    fn check_args(fixture: &ModFixture, command: &[&str]) -> (Args, CheckOptions) {
+        let args = args(fixture, command);
+        let Command::Check(ref opts) = args.cmd else {
+            panic!("not a check command");
+        };
+        let opts = opts.clone();
+        (args, opts)
+    }
+
+    fn args(fixture: &ModFixture, command: &[&str]) -> Args {
+        let mut argv = vec![
+            "mcm-meta-helper",
+            "--moddir",
+            fixture.path().to_str().unwrap(),
+        ];
+        argv.extend(command);
+        Args::parse_from(argv)
+    }
+
+    fn run_check(fixture: &ModFixture, command: &[&str]) -> bool {
+        let (args, opts) = check_args(fixture, command);
+        check_mod(&args, &opts, &mut fixture.moddir()).expect("running the check")
+    }
+
+    #[test]
+    fn misnamed_file_fails_a_single_language_check() {
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .file("mcm-meta-helper.toml", "required_languages = []\n")
+            .translation("MyMod_english.txt", &["$MyMod_Title\tTitle"])
+            .translation("MyMd_german.txt", &["$MyMod_Title\tTitel"]);
+        assert!(run_check(&fixture, &["check", "english"]));
+        assert!(!run_check(&fixture, &["check", "german"]));
+    }
+
+    #[test]
+    fn naming_violation_fails_a_single_language_check() {
+        let fixture = ModFixture::new(&["$MyMod_Title", "$Title"])
+            .file(
+                "mcm-meta-helper.toml",
+                "required_languages = []\nkey_prefix = \"$MyMod_\"\n",
+            )
+            .translation(
+                "MyMod_english.txt",
+                &["$MyMod_Title\tTitle", "$Title\tAlso a title"],
+            );
+        assert!(!run_check(&fixture, &["check", "english"]));
+        assert!(!run_check(&fixture, &["check", "all"]));
+    }
+
+    #[test]
+    fn against_a_baseline_still_fails_on_other_problems() {
+        let fixture = ModFixture::new(&["$MyMod_Title", "$MyMod_Help"])
+            .file("mcm-meta-helper.toml", "required_languages = []\n")
+            .translation("MyMod_english.txt", &["$MyMod_Title\tTitle"]);
+        let baseline = fixture.path().join("baseline.json");
+        let baseline = baseline.to_str().unwrap();
+        run_check(&fixture, &["check", "all", "--baseline", baseline]);
+        assert!(run_check(
+            &fixture,
+            &["check", "all", "--against", baseline]
+        ));
+
+        // A duplicate with a different value isn't something the baseline records.
+        let fixture = fixture.translation(
+            "MyMod_english.txt",
+            &["$MyMod_Title\tTitle", "$MyMod_Title\tOther title"],
+        );
+        assert!(!run_check(
+            &fixture,
+            &["check", "all", "--against", baseline]
+        ));
+    }
+
+    #[test]
+    fn update_skips_keys_provided_with_different_case() {
+        let fixture = ModFixture::new(&["$MyMod_A", "$MyMod_C"])
+            .file("mcm-meta-helper.toml", "required_languages = []\n")
+            .translation("MyMod_english.txt", &["$MyMod_A\tA", "$MyMod_C\tC"])
+            .translation("MyMod_german.txt", &["$mymod_c\tc"]);
+        assert!(update(&args(&fixture, &["update"]), false).unwrap());
+
+        let mut german = fixture
+            .moddir()
+            .translation_file_for("german")
+            .unwrap()
+            .unwrap();
+        let mut provided = german.provided_translations().unwrap();
+        provided.sort();
+        assert_eq!(
+            provided,
+            vec!["$MyMod_A".to_string(), "$mymod_c".to_string()]
+        );
+    }
+
+    #[test]
+    fn check_and_stats_agree_on_unused_keys() {
+        let fixture = ModFixture::new(&["$MyMod_A", "$MyMod_C"])
+            .file("mcm-meta-helper.toml", "required_languages = []\n")
+            .translation("MyMod_english.txt", &["$MyMod_A\tA", "$MyMod_C\tC"])
+            .translation(
+                "MyMod_german.txt",
+                &["$MyMod_A\ta", "$MyMod_C\tc", "$mymod_c\tc", "$MyMod_X\tx"],
+            );
+        let baseline = fixture.path().join("baseline.json");
+        run_check(
+            &fixture,
+            &["check", "german", "--baseline", baseline.to_str().unwrap()],
+        );
+        let checked = CheckResults::load(&baseline).unwrap();
+
+        let stats = gather_stats(&mut fixture.moddir()).unwrap();
+        assert_eq!(
+            checked.languages["german"].unused,
+            vec!["$MyMod_X".to_string()]
+        );
+        assert_eq!(stats["german"].unused, 1);
+    }
+
+    #[test]
+    fn builtin_overrides_fail_the_check_only_when_configured_to() {
+        let mut fixture = ModFixture::new(&["$MyMod_Title", "$Enabled"]).translation(
+            "MyMod_english.txt",
+            &["$MyMod_Title\tTitle", "$Enabled\tOn"],
+        );
+        for (severity, passes) in [("warning", true), ("error", false), ("off", true)] {
+            fixture = fixture.file(
+                "mcm-meta-helper.toml",
+                &format!("required_languages = []\nbuiltin_overrides = \"{severity}\"\n"),
+            );
+            assert_eq!(run_check(&fixture, &["check", "all"]), passes, "{severity}");
+        }
+    }
+
+    #[test]
+    fn created_language_files_use_the_source_files_plugin_name() {
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .file(
+                "mcm-meta-helper.toml",
+                "required_languages = [\"english\", \"german\"]\n",
+            )
+            .translation("My_Mod_English.txt", &["$MyMod_Title\tTitle"]);
+        create_missing_languages(&mut fixture.moddir()).unwrap();
+
+        let created = fixture.translations_dir().join("My_Mod_german.txt");
+        let mut german = Translation::new(created, "german");
+        assert_eq!(
+            german.translations().unwrap().get("$MyMod_Title"),
+            Some(&"Title".to_string())
+        );
+        assert!(fixture.moddir().missing_languages().unwrap().is_empty());
+    }
+}
@@ -1,20 +1,25 @@
 use eyre::{Report, Result};
+use globset::GlobSet;
 use once_cell::sync::Lazy;
 use serde_json::Value;
 use walkdir::WalkDir;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::SystemTime;
 
-use crate::Translation;
+use crate::{
+    builtin_keys, split_translation_filename, ProblemKind, ProjectConfig, Suppressions,
+    Translation,
+};
 
 pub static RIPGREP: Lazy<String> = Lazy::new(|| {
-    if let Ok(_) = Command::new("rg").arg("--version").output() {
+    if Command::new("rg").arg("--version").output().is_ok() {
         "rg".to_string()
-    } else if let Ok(_) = Command::new("rg.exe").arg("--version").output() {
+    } else if Command::new("rg.exe").arg("--version").output().is_ok() {
         "rg.exe".to_string()
     } else {
         "".to_string()
@@ -33,6 +38,26 @@ pub struct ModDirectory {
     translations: Option<HashMap<String, Translation>>,
     /// The discovered data directory for this mod tree.
     datadir: PathBuf,
+    /// Project settings from the mod's config file, if it has one.
+    config: ProjectConfig,
+    /// Compiled globs for paths to skip.
+    ignore: GlobSet,
+    /// Problems the suppressions file says not to report.
+    suppressions: Suppressions,
+    /// Keys SkyUI and other frameworks provide, so this mod doesn't need to.
+    builtin_keys: HashSet<String>,
+    /// The keys each json file requests, as of the stamp the file had when we read it.
+    parsed_jsons: HashMap<PathBuf, (FileStamp, Vec<String>)>,
+    /// Translation files already read, as of the stamp each had when we read it.
+    loaded_translations: HashMap<PathBuf, (FileStamp, Translation)>,
+}
+
+/// A file's modification time and size, to tell whether it changed since we read it.
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
 }
 
 impl ModDirectory {
@@ -42,10 +67,29 @@ impl ModDirectory {
         let lastbits: PathBuf = components.clone().rev().take(1).collect();
         let name = lastbits.display().to_string();
 
-        let datadir = find_data_dir(&modpath).expect(&format!(
-            "{} does not contain a valid MCM Helper-using mod.",
-            modpath.display()
-        ));
+        let config = ProjectConfig::load(&modpath)?;
+        let ignore = config.ignore_set()?;
+        let suppressions = Suppressions::load(&modpath.join(&config.suppressions))?;
+        let builtin_keys = builtin_keys(&config, &modpath)?;
+
+        let datadir = if let Some(ref configured) = config.data_dir {
+            let datadir = modpath.join(configured);
+            if !datadir.is_dir() {
+                return Err(eyre::eyre!(
+                    "The configured data directory {} does not exist.",
+                    datadir.display()
+                ));
+            }
+            datadir
+        } else {
+            let Some(datadir) = find_data_dir(&modpath, &modpath, &ignore) else {
+                return Err(eyre::eyre!(
+                    "{} does not contain a valid MCM Helper-using mod.",
+                    modpath.display()
+                ));
+            };
+            datadir
+        };
 
         Ok(Self {
             config_path: None,
@@ -53,22 +97,107 @@ impl ModDirectory {
             name,
             translations: None,
             datadir,
+            config,
+            ignore,
+            suppressions,
+            builtin_keys,
+            parsed_jsons: HashMap::new(),
+            loaded_translations: HashMap::new(),
         })
     }
 
-    pub fn translation_files(&mut self) -> Result<HashMap<String, Translation>> {
-        let search_dir: PathBuf = [
+    /// The project settings for this mod.
+    pub fn config(&self) -> &ProjectConfig {
+        &self.config
+    }
+
+    /// The full path to this mod's suppressions file, whether or not it exists.
+    pub fn suppressions_path(&self) -> PathBuf {
+        self.modpath.join(&self.config.suppressions)
+    }
+
+    /// The suppressions read from the suppressions file.
+    pub fn suppressions(&self) -> &Suppressions {
+        &self.suppressions
+    }
+
+    /// True if the project config or the suppressions file says not to report
+    /// this problem for this language.
+    pub fn is_suppressed(&self, kind: ProblemKind, language: &str, key: &str) -> bool {
+        if kind == ProblemKind::Unused && self.config.allowed_unused.iter().any(|xs| xs == key) {
+            return true;
+        }
+        self.suppressions.allows(kind, language, key)
+    }
+
+    /// Keys SkyUI and other frameworks provide, which this mod may request
+    /// without providing.
+    pub fn builtin_keys(&self) -> &HashSet<String> {
+        &self.builtin_keys
+    }
+
+    /// The mod's root directory.
+    pub fn modpath(&self) -> &Path {
+        &self.modpath
+    }
+
+    /// The mod's data directory, where config.json, translations, and scripts live.
+    pub fn datadir(&self) -> &Path {
+        &self.datadir
+    }
+
+    /// True if this path is one we never look at, like a build directory or
+    /// anything matching the ignore globs.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|xs| xs.starts_with(&self.modpath) && *xs != self.modpath)
+            .any(|xs| is_ignored(xs, &self.modpath, &self.ignore))
+    }
+
+    /// Forget what we read from these files, so the next look at them reads
+    /// them again.
+    pub fn forget(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.parsed_jsons.remove(path);
+            self.loaded_translations.remove(path);
+        }
+        self.translations = None;
+        self.config_path = None;
+    }
+
+    /// The directory where this mod's translation files live.
+    pub fn translations_dir(&self) -> PathBuf {
+        [
             self.datadir.clone(),
             PathBuf::from("Interface"),
             PathBuf::from("Translations"),
         ]
         .iter()
-        .collect();
+        .collect()
+    }
+
+    /// Required languages from the project config that have no translation file.
+    pub fn missing_languages(&mut self) -> Result<Vec<String>> {
+        let found = self.translation_files()?;
+        let mut missing: Vec<String> = self
+            .config
+            .required_languages
+            .iter()
+            .filter(|xs| !found.contains_key(xs.as_str()))
+            .cloned()
+            .collect();
+        missing.sort();
+        Ok(missing)
+    }
+
+    /// Every `.txt` file in the translations directory, whatever its name.
+    pub fn translation_file_paths(&self) -> Result<Vec<PathBuf>> {
+        let search_dir = self.translations_dir();
         if !search_dir.exists() {
-            return Ok(HashMap::new());
+            return Ok(Vec::new());
         }
 
-        let files: Vec<PathBuf> = std::fs::read_dir(search_dir)?
+        let mut files: Vec<PathBuf> = std::fs::read_dir(search_dir)?
             .filter_map(|xs| {
                 let Ok(entry) = xs else {
                     return None;
@@ -87,55 +216,213 @@ impl ModDirectory {
                 }
             })
             .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Every translation file named for a language, keyed by language. Files
+    /// unchanged since we last read them aren't read again.
+    pub fn translation_files(&mut self) -> Result<HashMap<String, Translation>> {
         let mut mapping = HashMap::new();
-        for file in files {
-            let Some(fname) = file.file_name() else {
+        for file in self.translation_file_paths()? {
+            let Some((_plugin, language)) = split_translation_filename(&file) else {
                 continue;
             };
-            let annoying = fname.to_string_lossy().replace(".txt", "");
-            let Some(pieces) = annoying.split_once('_') else {
-                continue;
+            let stamp = file_stamp(&file);
+            let translation = match self.loaded_translations.get(&file) {
+                Some((seen, translation)) if *seen == stamp => translation.clone(),
+                _ => {
+                    let mut translation = Translation::new(file.clone(), language.as_str());
+                    // Files that fail to load are left for the caller to report.
+                    if translation.load_translations().is_ok() {
+                        self.loaded_translations
+                            .insert(file, (stamp, translation.clone()));
+                    }
+                    translation
+                }
             };
-            let language = pieces.1;
-            let translation = Translation::new(file, language);
-            mapping.insert(pieces.1.to_owned(), translation);
+            mapping.insert(language, translation);
         }
         Ok(mapping)
     }
 
+    /// The names of the plugin files (esp, esm, esl) in the data directory, without extensions.
+    pub fn plugin_names(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = std::fs::read_dir(&self.datadir)?
+            .filter_map(|xs| {
+                let path = xs.ok()?.path();
+                let extension = path.extension()?.to_string_lossy().to_lowercase();
+                if ["esp", "esm", "esl"].contains(&extension.as_str()) {
+                    Some(path.file_stem()?.to_string_lossy().to_string())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// This mod's MCM Helper config.json, if it has one that parses. A config.json
+    /// that doesn't parse is left for `validate` to report.
+    fn parsed_config(&mut self) -> Result<Option<Value>> {
+        let Some(config) = self.find_config()? else {
+            return Ok(None);
+        };
+        let file = File::open(&config)?;
+        let rdr = std::io::BufReader::new(file);
+        match serde_json::from_reader(rdr) {
+            Ok(cfgjson) => Ok(Some(cfgjson)),
+            Err(e) => {
+                log::debug!("{} does not parse: {e}", config.display());
+                Ok(None)
+            }
+        }
+    }
+
+    /// The `modName` field from this mod's MCM Helper config.json, if it has one.
+    pub fn mcm_mod_name(&mut self) -> Result<Option<String>> {
+        let Some(cfgjson) = self.parsed_config()? else {
+            return Ok(None);
+        };
+        Ok(cfgjson
+            .get("modName")
+            .and_then(|xs| xs.as_str())
+            .map(|xs| xs.to_string()))
+    }
+
     pub fn relevant_jsons(&mut self) -> Result<Vec<PathBuf>> {
         todo!()
     }
 
-    pub fn all_needed_translations(&mut self) -> Result<Vec<String>> {
+    /// Every json file that can request translations: I4 files, config.json,
+    /// and any extra sources named in the project config.
+    pub fn key_source_files(&mut self) -> Result<Vec<PathBuf>> {
         let mut search_list = self.find_i4_jsons()?;
         if let Some(config) = self.find_config()? {
             search_list.push(config);
         };
+        search_list.extend(self.find_extra_sources()?);
+        Ok(search_list)
+    }
 
-        let mut requested: Vec<String> = search_list
-            .iter()
+    /// The translation keys requested by each json file. Files that can't be
+    /// read or parsed are skipped. Files unchanged since we last read them
+    /// aren't parsed again.
+    pub fn requested_keys_by_file(&mut self) -> Result<Vec<(PathBuf, Vec<String>)>> {
+        let requested = self
+            .key_source_files()?
+            .into_iter()
             .filter_map(|jpath| {
-                let file = File::open(jpath).ok()?;
+                let stamp = file_stamp(&jpath);
+                if let Some((seen, requested)) = self.parsed_jsons.get(&jpath) {
+                    if *seen == stamp {
+                        return Some((jpath, requested.clone()));
+                    }
+                }
+                let file = File::open(&jpath).ok()?;
                 let rdr = std::io::BufReader::new(file);
                 let cfgjson: serde_json::Value = serde_json::from_reader(rdr).ok()?;
                 let requested = collect_translation_keys(&cfgjson);
-                Some(requested)
+                self.parsed_jsons
+                    .insert(jpath.clone(), (stamp, requested.clone()));
+                Some((jpath, requested))
             })
-            .flatten()
+            .collect();
+        Ok(requested)
+    }
+
+    /// A mapping from each requested key to the files that request it.
+    pub fn requested_key_locations(&mut self) -> Result<HashMap<String, Vec<PathBuf>>> {
+        let mut locations: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for (jpath, keys) in self.requested_keys_by_file()? {
+            for key in keys {
+                let files = locations.entry(key).or_default();
+                if !files.contains(&jpath) {
+                    files.push(jpath.clone());
+                }
+            }
+        }
+        Ok(locations)
+    }
+
+    /// Every requested key once, in the order the json files request them.
+    pub fn requested_keys_in_order(&mut self) -> Result<Vec<String>> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let ordered = self
+            .requested_keys_by_file()?
+            .into_iter()
+            .flat_map(|(_jpath, keys)| keys)
+            .filter(|key| seen.insert(key.clone()))
+            .collect();
+        Ok(ordered)
+    }
+
+    /// Every requested key this mod must provide itself: everything requested
+    /// except the built-in keys, sorted.
+    pub fn required_translations(&mut self) -> Result<Vec<String>> {
+        let mut required = self.all_needed_translations()?;
+        required.retain(|xs| !self.builtin_keys.contains(xs));
+        required.dedup();
+        Ok(required)
+    }
+
+    pub fn all_needed_translations(&mut self) -> Result<Vec<String>> {
+        let mut requested: Vec<String> = self
+            .requested_keys_by_file()?
+            .into_iter()
+            .flat_map(|(_jpath, keys)| keys)
             .collect();
 
         requested.sort();
         Ok(requested)
     }
 
+    /// The translation keys config.json requests, grouped into sections: one for
+    /// the keys outside any page, titled with the mod name, then one per page,
+    /// titled with the page's display name key.
+    pub fn config_sections(&mut self) -> Result<Vec<(String, Vec<String>)>> {
+        let Some(Value::Object(mapping)) = self.parsed_config()? else {
+            return Ok(Vec::new());
+        };
+
+        let modname = mapping
+            .get("modName")
+            .and_then(|xs| xs.as_str())
+            .unwrap_or(self.name.as_str())
+            .to_string();
+        let mut toplevel = mapping.clone();
+        toplevel.remove("pages");
+        let mut sections = vec![(modname, keys_from_mapping(&toplevel))];
+
+        if let Some(Value::Array(pages)) = mapping.get("pages") {
+            for page in pages {
+                let title = page
+                    .get("pageDisplayName")
+                    .and_then(|xs| xs.as_str())
+                    .unwrap_or("page")
+                    .to_string();
+                sections.push((title, collect_translation_keys(page)));
+            }
+        }
+        Ok(sections)
+    }
+
+    /// A path for display, relative to the mod directory if it's inside it.
+    pub fn relative_display(&self, path: &Path) -> String {
+        path.strip_prefix(&self.modpath)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
     /// Search for the potentially unused tags in a source directory, skipping jsons.
-    /// If ripgrep isn't present (as either rg or rg.exe) this doesn't filter but
-    /// also does not trigger errors.
+    /// If ripgrep isn't present (as either rg or rg.exe) we read the source files
+    /// ourselves, which is slower but finds the same keys.
     pub fn ripgrep_search(&mut self, lookfor: Vec<String>) -> Vec<String> {
         // Test for ripgrep first.
         if RIPGREP.is_empty() {
-            return lookfor;
+            return self.scan_source_files(lookfor);
         }
 
         lookfor
@@ -147,6 +434,9 @@ impl ModDirectory {
                     .arg("-Tjson")
                     .arg("--glob")
                     .arg("!Translations");
+                for pattern in self.config.ignore.iter() {
+                    cmd.arg("--glob").arg(format!("!{pattern}"));
+                }
                 cmd.arg(escaped);
                 cmd.arg(self.modpath.to_string_lossy().to_string());
                 let Ok(status) = cmd.status() else {
@@ -159,6 +449,48 @@ impl ModDirectory {
             .collect()
     }
 
+    /// The keys that appear in no source file other than a json file, searching
+    /// every file's bytes for them.
+    fn scan_source_files(&self, lookfor: Vec<String>) -> Vec<String> {
+        let mut unfound = lookfor;
+        for fpath in self.source_files() {
+            if unfound.is_empty() {
+                break;
+            }
+            let extension = fpath.extension().unwrap_or_default().to_string_lossy();
+            if extension.eq_ignore_ascii_case("json") {
+                continue;
+            }
+            let Ok(bytes) = std::fs::read(&fpath) else {
+                continue;
+            };
+            unfound.retain(|key| {
+                !bytes
+                    .windows(key.len())
+                    .any(|window| window == key.as_bytes())
+            });
+        }
+        unfound
+    }
+
+    /// Every file in the mod directory outside the translations directory,
+    /// skipping anything ignored.
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        let translations = self.translations_dir();
+        let mut files: Vec<PathBuf> = WalkDir::new(&self.modpath)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| {
+                e.path() != translations && !is_ignored(e.path(), &self.modpath, &self.ignore)
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        files.sort();
+        files
+    }
+
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
@@ -263,6 +595,33 @@ impl ModDirectory {
             .collect();
         Ok(files)
     }
+
+    /// Find the additional json files named by the `extra_sources` globs in the
+    /// project config, skipping anything ignored.
+    pub fn find_extra_sources(&self) -> Result<Vec<PathBuf>, Report> {
+        if self.config.extra_sources.is_empty() {
+            return Ok(Vec::new());
+        }
+        let wanted = self.config.extra_sources_set()?;
+
+        let mut files: Vec<PathBuf> = WalkDir::new(&self.modpath)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| !is_ignored(e.path(), &self.modpath, &self.ignore))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(&self.modpath).ok()?;
+                if wanted.is_match(relative) {
+                    Some(e.path().to_path_buf())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
 }
 
 fn collect_translation_keys(value: &serde_json::Value) -> Vec<String> {
@@ -316,12 +675,29 @@ fn keys_from_array(arr: &[Value]) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
-/// Directories to skip.
+/// Directories to skip, in addition to any ignore globs in the project config.
 const IGNORE_DIRS: [&str; 3] = ["target", "build", "extern"];
 
+/// Check a path against the hard-coded list of directories to skip and the
+/// ignore globs from the project config, which are relative to the mod root.
+fn is_ignored(path: &Path, top: &Path, ignore: &GlobSet) -> bool {
+    let basename = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    if basename.starts_with('.') || IGNORE_DIRS.contains(&basename.as_str()) {
+        return true;
+    }
+    match path.strip_prefix(top) {
+        Ok(relative) => ignore.is_match(relative),
+        Err(_) => false,
+    }
+}
+
 /// Filter a list of immediate subdirectories of a given directory for only
 /// directories relevant for considering as potential data dirs.
-fn find_relevant_dirs(path: &PathBuf) -> Vec<PathBuf> {
+fn find_relevant_dirs(path: &PathBuf, top: &Path, ignore: &GlobSet) -> Vec<PathBuf> {
     WalkDir::new(path)
         .min_depth(1)
         .max_depth(1)
@@ -329,15 +705,7 @@ fn find_relevant_dirs(path: &PathBuf) -> Vec<PathBuf> {
         .filter_map(|e| e.ok())
         .filter(|e| e.path().is_dir())
         .filter_map(|e| {
-            let basename = e
-                .path()
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_lowercase();
-            if basename.starts_with(".") {
-                None
-            } else if IGNORE_DIRS.contains(&basename.as_str()) {
+            if is_ignored(e.path(), top, ignore) {
                 None
             } else {
                 Some(e.path().to_path_buf())
@@ -349,34 +717,96 @@ fn find_relevant_dirs(path: &PathBuf) -> Vec<PathBuf> {
 /// Look for the two required mcm-helper subdirs in a list of subdirectories,
 /// returning true if they're found.
 fn is_data_dir(dirs: &[PathBuf]) -> bool {
-    if dirs.iter().any(|e| {
+    dirs.iter().any(|e| {
         e.file_name()
             .unwrap_or_default()
             .to_str()
             .unwrap_or_default()
             .to_lowercase()
             == "interface"
-    }) {
-        true
-    } else {
-        false
-    }
+    })
 }
 
 /// Find a subdirectory of moddir that has both "interface" and "translations"
 /// as subdirectories (names case-insensitive). This is our starting point for
 /// finding config.json. If we don't find one, this mod directory is not valid
 /// for us, because we need mcm config and translation files to do our work.
-fn find_data_dir(top: &PathBuf) -> Option<PathBuf> {
-    let relevant = find_relevant_dirs(top);
+fn find_data_dir(dir: &PathBuf, top: &Path, ignore: &GlobSet) -> Option<PathBuf> {
+    let relevant = find_relevant_dirs(dir, top, ignore);
     if is_data_dir(relevant.as_slice()) {
-        Some(top.clone())
+        Some(dir.clone())
     } else {
         for entry in relevant {
-            if let Some(found) = find_data_dir(&entry) {
+            if let Some(found) = find_data_dir(&entry, top, ignore) {
                 return Some(found);
             }
         }
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn mcm_mod_name_reads_config_json() {
+        let fixture = ModFixture::new(&["$MyMod_Title"]);
+        let mut moddir = fixture.moddir();
+        assert_eq!(moddir.mcm_mod_name().unwrap().as_deref(), Some("MyMod"));
+    }
+
+    #[test]
+    fn mcm_mod_name_ignores_a_config_that_does_not_parse() {
+        let fixture = ModFixture::with_config("{ \"modName\": \"MyMod\", ");
+        let mut moddir = fixture.moddir();
+        assert_eq!(moddir.mcm_mod_name().unwrap(), None);
+    }
+
+    #[test]
+    fn config_sections_group_keys_by_page() {
+        let fixture = ModFixture::with_config(
+            r#"{
+                "modName": "MyMod",
+                "displayName": "$MyMod_Name",
+                "pages": [
+                    { "pageDisplayName": "$MyMod_General", "content": [{ "text": "$MyMod_Enabled" }] },
+                    { "pageDisplayName": "$MyMod_Advanced", "content": [{ "text": "$MyMod_Count" }] }
+                ]
+            }"#,
+        );
+        let sections = fixture.moddir().config_sections().unwrap();
+        let titles: Vec<&str> = sections.iter().map(|(title, _keys)| title.as_str()).collect();
+        assert_eq!(titles, ["MyMod", "$MyMod_General", "$MyMod_Advanced"]);
+        assert_eq!(sections[0].1, ["$MyMod_Name"]);
+        assert!(sections[2].1.contains(&"$MyMod_Count".to_string()));
+    }
+
+    #[test]
+    fn config_sections_ignore_a_config_that_does_not_parse() {
+        let fixture = ModFixture::with_config("{ \"modName\": \"MyMod\", ");
+        assert!(fixture.moddir().config_sections().unwrap().is_empty());
+    }
+
+    #[test]
+    fn ignore_globs_skip_a_data_directory() {
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .file("old/Interface/Translations/MyMod_english.txt", "")
+            .file("mcm-meta-helper.toml", "ignore = [\"data\"]\n");
+        let moddir = fixture.moddir();
+        assert_eq!(moddir.datadir(), fixture.path().canonicalize().unwrap().join("old"));
+    }
+
+    #[test]
+    fn is_ignored_checks_every_directory_on_the_way_down() {
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .file("mcm-meta-helper.toml", "ignore = [\"data/Scripts\"]\n");
+        let moddir = fixture.moddir();
+        let top = fixture.path().canonicalize().unwrap();
+        assert!(moddir.is_ignored(&top.join("data/Scripts/Source/MyMod.psc")));
+        assert!(moddir.is_ignored(&top.join("build/MyMod.zip")));
+        assert!(moddir.is_ignored(&top.join(".git/config")));
+        assert!(!moddir.is_ignored(&top.join("data/Interface/Translations/MyMod_english.txt")));
+    }
+
+}
@@ -0,0 +1,311 @@
+//! Every mod's translation keys share one namespace in the game, so a key
+//! named `$ShowHelp` in two mods means one of them shows the other's text.
+//! Check keys against a naming convention, usually a per-mod prefix, and
+//! rename the ones that break it.
+
+use std::collections::HashSet;
+
+use eyre::{Report, Result};
+use owo_colors::OwoColorize;
+use regex::Regex;
+
+use crate::{apply_renames, report_renamed, Args, ModDirectory};
+
+/// The naming convention from the project config.
+#[derive(Debug, Clone)]
+pub struct NamingRule {
+    /// The prefix every key must start with, with `{modName}` filled in.
+    prefix: Option<String>,
+    /// The pattern every key must match.
+    pattern: Option<Regex>,
+}
+
+/// A key that breaks the naming convention.
+#[derive(Debug, Clone)]
+pub struct NamingProblem {
+    pub key: String,
+    /// What's wrong with the name.
+    pub problem: String,
+    /// A name that follows the convention, if we can come up with one.
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for NamingProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.problem)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, "; rename to {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+impl NamingRule {
+    /// Build the naming rule from the project config, or None if the config
+    /// doesn't set one. The mod name for `{modName}` comes from config.json,
+    /// falling back to the mod directory's name.
+    pub fn for_mod(moddir: &mut ModDirectory) -> Result<Option<Self>> {
+        let pattern = moddir.config().key_regex()?;
+        let prefix = match moddir.config().key_prefix.clone() {
+            Some(prefix) if prefix.contains("{modName}") => {
+                let modname = moddir
+                    .mcm_mod_name()?
+                    .unwrap_or_else(|| moddir.name().to_string());
+                Some(prefix.replace("{modName}", modname.as_str()))
+            }
+            prefix => prefix,
+        };
+        if prefix.is_none() && pattern.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { prefix, pattern }))
+    }
+
+    /// What's wrong with this key's name, if anything.
+    pub fn problem(&self, key: &str) -> Option<String> {
+        if let Some(ref prefix) = self.prefix {
+            if !key.starts_with(prefix.as_str()) {
+                return Some(format!("does not start with {prefix}"));
+            }
+        }
+        if let Some(ref pattern) = self.pattern {
+            if !pattern.is_match(key) {
+                return Some(format!("does not match {}", pattern.as_str()));
+            }
+        }
+        None
+    }
+
+    /// Suggest a name that follows the convention. Only a prefix rule can
+    /// suggest names: a key with the prefix in the wrong case or without its
+    /// trailing underscore gets it fixed, and any other key gets it added.
+    pub fn suggested_name(&self, key: &str) -> Option<String> {
+        let prefix = self.prefix.as_ref()?;
+        let bare_prefix = prefix.trim_end_matches('_');
+        let folded = key.to_lowercase();
+        let rest = if folded.starts_with(prefix.to_lowercase().as_str()) {
+            key.get(prefix.len()..).unwrap_or_default()
+        } else if bare_prefix.len() < prefix.len()
+            && folded.starts_with(bare_prefix.to_lowercase().as_str())
+        {
+            key.get(bare_prefix.len()..).unwrap_or_default()
+        } else {
+            key.trim_start_matches('$')
+        };
+        if rest.is_empty() {
+            return None;
+        }
+        let suggestion = format!("{prefix}{rest}");
+        if self.problem(suggestion.as_str()).is_some() {
+            return None;
+        }
+        Some(suggestion)
+    }
+}
+
+/// Check every key the mod requests or provides against the naming convention,
+/// sorted by key. Built-in keys are skipped, since they aren't the mod's to name.
+pub fn naming_problems(
+    rule: &NamingRule,
+    keys: &HashSet<String>,
+    builtin: &HashSet<String>,
+) -> Vec<NamingProblem> {
+    let mut problems: Vec<NamingProblem> = keys
+        .iter()
+        .filter(|key| !builtin.contains(*key))
+        .filter_map(|key| {
+            let problem = rule.problem(key)?;
+            Some(NamingProblem {
+                key: key.clone(),
+                problem,
+                suggestion: rule.suggested_name(key),
+            })
+        })
+        .collect();
+    problems.sort_by(|a, b| a.key.cmp(&b.key));
+    problems
+}
+
+/// Every key the mod requests, plus every key any translation file provides.
+pub fn all_mod_keys(moddir: &mut ModDirectory) -> Result<HashSet<String>> {
+    let mut keys: HashSet<String> = HashSet::from_iter(moddir.all_needed_translations()?);
+    for trfile in moddir.translation_files()?.values_mut() {
+        keys.extend(trfile.provided_translations()?);
+    }
+    Ok(keys)
+}
+
+/// Rename every key that breaks the naming convention to the suggested name.
+pub fn fix_names(args: &Args, dry_run: bool) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let Some(rule) = NamingRule::for_mod(&mut moddir)? else {
+        return Err(eyre::eyre!(
+            "No naming convention is configured; set key_prefix or key_pattern in mcm-meta-helper.toml."
+        ));
+    };
+    let keys = all_mod_keys(&mut moddir)?;
+    let problems = naming_problems(&rule, &keys, moddir.builtin_keys());
+    if problems.is_empty() {
+        log::info!("Every key follows the naming convention.");
+        return Ok(true);
+    }
+
+    let mut renames: Vec<(String, String)> = Vec::new();
+    let mut unfixed: Vec<&NamingProblem> = Vec::new();
+    for problem in problems.iter() {
+        match problem.suggestion {
+            Some(ref suggestion)
+                if !keys.contains(suggestion)
+                    && !renames.iter().any(|(_old, new)| new == suggestion) =>
+            {
+                renames.push((problem.key.clone(), suggestion.clone()));
+            }
+            _ => unfixed.push(problem),
+        }
+    }
+
+    if !renames.is_empty() {
+        let touched = apply_renames(&mut moddir, &renames, dry_run)?;
+        report_renamed(&touched);
+        log::info!(
+            "{} {} keys in {} files.",
+            if dry_run { "Would rename" } else { "Renamed" },
+            renames.len().bold(),
+            touched.len().bold()
+        );
+    }
+    for problem in unfixed.iter() {
+        log::warn!(
+            "{}: {}; rename it by hand with rename-key.",
+            problem.key.bold(),
+            problem.problem
+        );
+    }
+
+    Ok(unfixed.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    fn prefix_rule(prefix: &str) -> NamingRule {
+        NamingRule {
+            prefix: Some(prefix.to_string()),
+            pattern: None,
+        }
+    }
+
+    fn pattern_rule(pattern: &str) -> NamingRule {
+        NamingRule {
+            prefix: None,
+            pattern: Some(Regex::new(pattern).unwrap()),
+        }
+    }
+
+    #[test]
+    fn prefix_rule_adds_or_fixes_the_prefix() {
+        let rule = prefix_rule("$MyMod_");
+        assert_eq!(rule.problem("$MyMod_Title"), None);
+        assert_eq!(
+            rule.problem("$Title").as_deref(),
+            Some("does not start with $MyMod_")
+        );
+        assert_eq!(
+            rule.suggested_name("$Title").as_deref(),
+            Some("$MyMod_Title")
+        );
+        assert_eq!(
+            rule.suggested_name("$mymod_Title").as_deref(),
+            Some("$MyMod_Title")
+        );
+        assert_eq!(
+            rule.suggested_name("$MyModTitle").as_deref(),
+            Some("$MyMod_Title")
+        );
+        assert_eq!(rule.suggested_name("$mymod_"), None);
+    }
+
+    #[test]
+    fn pattern_rule_reports_but_cannot_suggest() {
+        let rule = pattern_rule(r"^\$MyMod_[A-Za-z]+$");
+        assert_eq!(rule.problem("$MyMod_Title"), None);
+        assert_eq!(
+            rule.problem("$MyMod_Title2").as_deref(),
+            Some(r"does not match ^\$MyMod_[A-Za-z]+$")
+        );
+        assert_eq!(rule.suggested_name("$MyMod_Title2"), None);
+    }
+
+    #[test]
+    fn suggestions_must_follow_the_whole_convention() {
+        let rule = NamingRule {
+            prefix: Some("$MyMod_".to_string()),
+            pattern: Some(Regex::new(r"^\$MyMod_[A-Za-z]+$").unwrap()),
+        };
+        assert_eq!(
+            rule.suggested_name("$Title").as_deref(),
+            Some("$MyMod_Title")
+        );
+        assert_eq!(rule.suggested_name("$Title_2"), None);
+    }
+
+    #[test]
+    fn naming_problems_skip_valid_and_builtin_keys() {
+        let rule = prefix_rule("$MyMod_");
+        let keys = HashSet::from_iter(
+            ["$MyMod_Title", "$Title", "$Enabled", "$mymod_Help"].map(String::from),
+        );
+        let builtin = HashSet::from(["$Enabled".to_string()]);
+        let problems: Vec<String> = naming_problems(&rule, &keys, &builtin)
+            .iter()
+            .map(|xs| xs.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "$Title: does not start with $MyMod_; rename to $MyMod_Title",
+                "$mymod_Help: does not start with $MyMod_; rename to $MyMod_Help",
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_uses_the_mod_name_from_config_json() {
+        let fixture = ModFixture::new(&["$Title"])
+            .file("mcm-meta-helper.toml", "key_prefix = \"${modName}_\"\n");
+        let rule = NamingRule::for_mod(&mut fixture.moddir()).unwrap().unwrap();
+        assert_eq!(rule.prefix.as_deref(), Some("$MyMod_"));
+
+        let fixture = ModFixture::new(&["$Title"]);
+        assert!(NamingRule::for_mod(&mut fixture.moddir())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn fix_names_renames_what_it_can() {
+        let fixture = ModFixture::new(&["$Title", "$Help", "$MyMod_Help"])
+            .file(
+                "mcm-meta-helper.toml",
+                "required_languages = []\nkey_prefix = \"$MyMod_\"\n",
+            )
+            .translation(
+                "MyMod_english.txt",
+                &["$Title\tTitle", "$Help\tHelp", "$MyMod_Help\tMore help"],
+            );
+        let moddir = fixture.path().to_str().unwrap();
+        let args = Args::parse_from(["mcm-meta-helper", "--moddir", moddir, "fix-names"]);
+
+        // $Help can't become $MyMod_Help, which is already taken.
+        assert!(!fix_names(&args, false).unwrap());
+        let keys = all_mod_keys(&mut fixture.moddir()).unwrap();
+        assert_eq!(
+            keys,
+            HashSet::from_iter(["$MyMod_Title", "$Help", "$MyMod_Help"].map(String::from))
+        );
+    }
+}
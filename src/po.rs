@@ -0,0 +1,339 @@
+//! Gettext PO and POT files, for tools like Poedit and Weblate. Each entry's
+//! `msgid` is the translation key and its `msgctxt` is the source language
+//! text, so translators see what they're translating and the key stays put.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result};
+
+use crate::{
+    language_code, language_for_code, language_from_filename, ExchangeSet, ImportedTranslations,
+};
+
+/// Write a POT template with every requested key, and a PO file for every
+/// language with the translations it already has.
+pub fn write_po_files(set: &ExchangeSet, output: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    let template = output.join(format!("{}.pot", set.name));
+    write_po(set, None, &template)?;
+    written.push(template);
+    for language in set.languages.iter() {
+        let fpath = output.join(format!("{}_{language}.po", set.name));
+        write_po(set, Some(language.as_str()), &fpath)?;
+        written.push(fpath);
+    }
+    Ok(written)
+}
+
+fn write_po(set: &ExchangeSet, language: Option<&str>, fpath: &Path) -> Result<()> {
+    let mut header = vec![
+        format!("Project-Id-Version: {}\n", set.name),
+        "MIME-Version: 1.0\n".to_string(),
+        "Content-Type: text/plain; charset=UTF-8\n".to_string(),
+        "Content-Transfer-Encoding: 8bit\n".to_string(),
+        format!(
+            "X-Source-Language: {}\n",
+            language_code(&set.source_language).unwrap_or(set.source_language.as_str())
+        ),
+    ];
+    if let Some(language) = language {
+        // Gettext writes regions with an underscore. Languages without a code
+        // are known by our own field alone.
+        if let Some(code) = language_code(language) {
+            header.insert(1, format!("Language: {}\n", code.replace('-', "_")));
+        }
+        header.push(format!("X-Skyrim-Language: {language}\n"));
+    }
+
+    let mut out = String::new();
+    out.push_str("msgid \"\"\nmsgstr \"\"\n");
+    for line in header {
+        out.push_str(&quoted(&line));
+        out.push('\n');
+    }
+    for entry in set.entries.iter() {
+        out.push('\n');
+        if !entry.locations.is_empty() {
+            out.push_str(&format!("#: {}\n", entry.locations.join(" ")));
+        }
+        if let Some(ref source) = entry.source {
+            out.push_str(&format!("msgctxt {}\n", quoted(source)));
+        }
+        out.push_str(&format!("msgid {}\n", quoted(&entry.key)));
+        let translation = language
+            .and_then(|xs| entry.translations.get(xs))
+            .map(|xs| xs.as_str())
+            .unwrap_or_default();
+        out.push_str(&format!("msgstr {}\n", quoted(translation)));
+    }
+    std::fs::write(fpath, out).context(format!("writing {}", fpath.display()))?;
+    Ok(())
+}
+
+fn quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn unquoted(text: &str) -> Option<String> {
+    let inner = text.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+/// One entry in a PO file, as far as we care.
+#[derive(Debug, Default)]
+struct PoEntry {
+    msgid: Option<String>,
+    msgstr: Option<String>,
+    fuzzy: bool,
+}
+
+/// Which string a continuation line adds to.
+enum Field {
+    Context,
+    Id,
+    Str,
+    Ignored,
+}
+
+/// Read the translations from a PO file. Fuzzy and empty translations are
+/// skipped, since nobody has finished them.
+pub fn read_po(fpath: &Path) -> Result<Vec<ImportedTranslations>> {
+    let text = std::fs::read_to_string(fpath).context(format!("reading {}", fpath.display()))?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    let mut entries: Vec<PoEntry> = Vec::new();
+    let mut entry = PoEntry::default();
+    let mut field = Field::Ignored;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let malformed = || {
+            eyre::eyre!(
+                "{} line {} is not valid PO: {line}",
+                fpath.display(),
+                idx + 1
+            )
+        };
+        if line.starts_with('"') {
+            let more = unquoted(line).ok_or_else(malformed)?;
+            match field {
+                Field::Id => entry.msgid.get_or_insert_with(String::new).push_str(&more),
+                Field::Str => entry.msgstr.get_or_insert_with(String::new).push_str(&more),
+                Field::Context | Field::Ignored => {}
+            }
+            continue;
+        }
+        // Anything else after a translation begins the next entry.
+        if entry.msgstr.is_some() {
+            entries.push(std::mem::take(&mut entry));
+            field = Field::Ignored;
+        }
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("#,") {
+            entry.fuzzy = flags.split(',').any(|xs| xs.trim() == "fuzzy");
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let (keyword, rest) = line.split_once(char::is_whitespace).ok_or_else(malformed)?;
+        let value = unquoted(rest).ok_or_else(malformed)?;
+        field = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => {
+                entry.msgid = Some(value);
+                Field::Id
+            }
+            "msgstr" | "msgstr[0]" => {
+                entry.msgstr = Some(value);
+                Field::Str
+            }
+            _ => Field::Ignored,
+        };
+    }
+    if entry.msgstr.is_some() {
+        entries.push(entry);
+    }
+
+    let mut language = None;
+    let mut values = Vec::new();
+    for entry in entries {
+        let (Some(key), Some(value)) = (entry.msgid, entry.msgstr) else {
+            continue;
+        };
+        if key.is_empty() {
+            language = header_language(&value);
+            continue;
+        }
+        if entry.fuzzy || value.trim().is_empty() {
+            continue;
+        }
+        values.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    let Some(language) = language.or_else(|| language_from_filename(fpath)) else {
+        return Err(eyre::eyre!(
+            "Can't tell which language {} is for; give it a Language header or name it <ModName>_<language>.po.",
+            fpath.display()
+        ));
+    };
+    Ok(vec![ImportedTranslations { language, values }])
+}
+
+/// The language a PO header names, preferring our own Skyrim language field.
+fn header_language(header: &str) -> Option<String> {
+    let field = |name: &str| {
+        header.lines().find_map(|xs| {
+            let (key, value) = xs.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .then(|| value.trim().to_string())
+        })
+    };
+    field("X-Skyrim-Language")
+        .map(|xs| xs.to_lowercase())
+        .or_else(|| field("Language").and_then(|xs| language_for_code(&xs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str, filename: &str) -> Result<Vec<ImportedTranslations>> {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = dir.path().join(filename);
+        std::fs::write(&fpath, text).unwrap();
+        read_po(&fpath)
+    }
+
+    fn values(text: &str) -> Vec<(String, String)> {
+        let imported = read(text, "MyMod_german.po").unwrap();
+        assert_eq!(imported.len(), 1);
+        imported.into_iter().next().unwrap().values
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn quoting_round_trips_escapes() {
+        let text = "say \"hi\"\\n\tnow\\";
+        let quoted = quoted(text);
+        assert_eq!(quoted, r#""say \"hi\"\\n\tnow\\""#);
+        assert_eq!(unquoted(&quoted).as_deref(), Some(text));
+        assert_eq!(unquoted(r#""a\nb""#).as_deref(), Some("a\nb"));
+        assert_eq!(unquoted("no quotes"), None);
+        assert_eq!(unquoted(r#""dangling\""#), None);
+    }
+
+    #[test]
+    fn reads_continuation_lines() {
+        let text = r#"msgid ""
+msgstr ""
+"Language: de\n"
+
+msgctxt "A long "
+"source"
+msgid "$MyMod_"
+"Long"
+msgstr ""
+"Ein langer "
+"Text"
+"#;
+        assert_eq!(values(text), [pair("$MyMod_Long", "Ein langer Text")]);
+    }
+
+    #[test]
+    fn reads_the_language_from_a_fuzzy_header() {
+        let text = r#"#, fuzzy
+msgid ""
+msgstr ""
+"Language: fr_FR\n"
+
+msgid "$MyMod_Title"
+msgstr "Titre"
+"#;
+        let imported = read(text, "translations.po").unwrap();
+        assert_eq!(imported[0].language, "french");
+        assert_eq!(imported[0].values, [pair("$MyMod_Title", "Titre")]);
+    }
+
+    #[test]
+    fn skips_fuzzy_empty_and_obsolete_entries() {
+        let text = r#"msgid ""
+msgstr "X-Skyrim-Language: german\n"
+
+#, fuzzy
+msgid "$MyMod_Guess"
+msgstr "Vermutung"
+
+msgid "$MyMod_Empty"
+msgstr ""
+
+#~ msgid "$MyMod_Old"
+#~ msgstr "Alt"
+
+#: data/mcm/config/MyMod/config.json:3
+#, c-format
+msgid "$MyMod_Title"
+msgstr "Titel"
+"#;
+        assert_eq!(values(text), [pair("$MyMod_Title", "Titel")]);
+    }
+
+    #[test]
+    fn reads_the_first_plural_form() {
+        let text = r#"msgid ""
+msgstr "Language: de\n"
+
+msgid "$MyMod_Items"
+msgid_plural "$MyMod_Items"
+msgstr[0] "Gegenstand"
+msgstr[1] "Gegenstände"
+"#;
+        assert_eq!(values(text), [pair("$MyMod_Items", "Gegenstand")]);
+    }
+
+    #[test]
+    fn needs_a_language() {
+        let text = "msgid \"$MyMod_Title\"\nmsgstr \"Titel\"\n";
+        assert!(read(text, "translations.po").is_err());
+        let imported = read(text, "MyMod_de.po").unwrap();
+        assert_eq!(imported[0].language, "german");
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let text = "msgid \"$MyMod_Title\"\nmsgstr Titel\n";
+        let message = read(text, "MyMod_german.po").unwrap_err().to_string();
+        assert!(message.contains("line 2 is not valid PO"), "{message}");
+    }
+}
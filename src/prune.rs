@@ -0,0 +1,173 @@
+//! Remove translations nothing uses from every translation file.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+use comfy_table::presets::NOTHING;
+use comfy_table::*;
+use eyre::{Context, Report, Result};
+use owo_colors::OwoColorize;
+
+use crate::{
+    grid_string, inline_suppressions, Args, ModDirectory, ProblemKind, Suppressions, Translation,
+};
+
+/// Find the keys a translation file provides that nothing requests, with the
+/// same rules `check` uses: suppressed keys and keys that differ from a requested
+/// key only in case are kept, and so is anything a full-text search finds.
+pub fn confirmed_unused(
+    moddir: &mut ModDirectory,
+    requested: &HashSet<String>,
+    trfile: &mut Translation,
+) -> Result<Vec<String>> {
+    let language = trfile.language().to_string();
+    let mut inline = Suppressions::default();
+    inline.extend(inline_suppressions(language.as_str(), trfile.lines()));
+    let folded: HashSet<String> = requested.iter().map(|xs| xs.to_lowercase()).collect();
+
+    let candidates: Vec<String> = trfile
+        .provided_translations()?
+        .into_iter()
+        .filter(|key| {
+            !requested.contains(key)
+                && !folded.contains(&key.to_lowercase())
+                && !inline.allows(ProblemKind::Unused, language.as_str(), key)
+                && !moddir.is_suppressed(ProblemKind::Unused, language.as_str(), key)
+        })
+        .collect();
+    let mut unused = moddir.ripgrep_search(candidates);
+    unused.sort();
+    Ok(unused)
+}
+
+/// Ask a yes-or-no question on the terminal. Anything but yes is no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Remove confirmed-unused translations from every translation file, backing up
+/// each file changed.
+pub fn prune(args: &Args, dry_run: bool, yes: bool) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let requested = moddir
+        .all_needed_translations()
+        .context("Finding all requested translations")?;
+    let requested: HashSet<String> = HashSet::from_iter(requested);
+
+    let mut table = Table::new();
+    table
+        .load_preset(NOTHING)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let mut plan: Vec<(Translation, Vec<String>)> = Vec::new();
+    let mut trfiles: Vec<(String, Translation)> = moddir.translation_files()?.into_iter().collect();
+    trfiles.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_language, mut trfile) in trfiles {
+        let unused = confirmed_unused(&mut moddir, &requested, &mut trfile)?;
+        if unused.is_empty() {
+            log::debug!("{}: nothing to prune", trfile.display().bold().blue());
+            continue;
+        }
+        table.add_row(vec![
+            Cell::new(trfile.display())
+                .fg(Color::Blue)
+                .add_attribute(Attribute::Bold),
+            Cell::new(grid_string(&unused, 30).trim()).set_delimiter('\n'),
+        ]);
+        plan.push((trfile, unused));
+    }
+
+    if plan.is_empty() {
+        log::info!("No unused translations to prune.");
+        return Ok(true);
+    }
+    let total: usize = plan.iter().map(|(_trfile, keys)| keys.len()).sum();
+    log::warn!("{table}");
+
+    if dry_run {
+        log::info!(
+            "Would remove {} unused translations from {} files.",
+            total.bold(),
+            plan.len().bold()
+        );
+        return Ok(true);
+    }
+    if !yes
+        && !confirm(
+            format!(
+                "Remove {total} unused translations from {} files?",
+                plan.len()
+            )
+            .as_str(),
+        )?
+    {
+        log::info!("Nothing pruned.");
+        return Ok(true);
+    }
+
+    for (mut trfile, keys) in plan {
+        let backup = trfile.backup()?;
+        let removed = trfile.remove_keys(&keys)?;
+        trfile.write()?;
+        log::info!(
+            "{}: {} lines removed; backup in {}",
+            trfile.display().bold().blue(),
+            removed,
+            backup.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn keys_used_by_scripts_are_kept() {
+        let fixture = ModFixture::new(&["$MyMod_Title"])
+            .file(
+                "data/Scripts/Source/MyMod.psc",
+                "string s = \"$MyMod_Dynamic\"\n",
+            )
+            .translation(
+                "MyMod_english.txt",
+                &[
+                    "$MyMod_Title\tTitle",
+                    "$MyMod_Dynamic\tSet by a script",
+                    "$MyMod_Old\tNothing uses this",
+                ],
+            );
+        let mut moddir = fixture.moddir();
+        let requested = HashSet::from_iter(moddir.all_needed_translations().unwrap());
+        let mut trfile = moddir.translation_file_for("english").unwrap().unwrap();
+
+        let unused = confirmed_unused(&mut moddir, &requested, &mut trfile).unwrap();
+        assert_eq!(unused, vec!["$MyMod_Old".to_string()]);
+    }
+
+    #[test]
+    fn suppressed_and_miscased_keys_are_kept() {
+        let fixture = ModFixture::new(&["$MyMod_Title"]).translation(
+            "MyMod_english.txt",
+            &[
+                "$mymod_title\tTitle",
+                "; mcm-meta-helper: allow-unused $MyMod_Kept",
+                "$MyMod_Kept\tKept on purpose",
+                "$MyMod_Old\tNothing uses this",
+            ],
+        );
+        let mut moddir = fixture.moddir();
+        let requested = HashSet::from_iter(moddir.all_needed_translations().unwrap());
+        let mut trfile = moddir.translation_file_for("english").unwrap().unwrap();
+
+        let unused = confirmed_unused(&mut moddir, &requested, &mut trfile).unwrap();
+        assert_eq!(unused, vec!["$MyMod_Old".to_string()]);
+    }
+}
@@ -0,0 +1,329 @@
+//! Rename translation keys everywhere a mod uses them: json files, Papyrus
+//! sources, the project's own settings, and every translation file.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use comfy_table::presets::NOTHING;
+use comfy_table::*;
+use eyre::{Context, Report, Result};
+use owo_colors::OwoColorize;
+
+use crate::{Args, ModDirectory};
+
+/// Compiled files that may contain a key but that we can't safely edit.
+const COMPILED_EXTENSIONS: [&str; 4] = ["pex", "esp", "esm", "esl"];
+/// Archives, too big to read and impossible to edit in place.
+const ARCHIVE_EXTENSIONS: [&str; 2] = ["bsa", "ba2"];
+
+/// A file a rename changed, or would change.
+#[derive(Debug, Clone)]
+pub struct RenamedFile {
+    /// The file's path, relative to the mod directory.
+    pub display: String,
+    /// How many keys were replaced.
+    pub replacements: usize,
+}
+
+/// True if this byte can continue a key, so a match ending just before it is
+/// really a longer key that merely starts with the one we're looking for.
+fn continues_key(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+/// Replace every whole-key appearance of each old key with its new name. Works
+/// on bytes, so text in any ASCII-compatible encoding keeps its encoding.
+pub fn replace_keys(text: &[u8], renames: &[(String, String)]) -> (Vec<u8>, usize) {
+    let mut output: Vec<u8> = Vec::with_capacity(text.len());
+    let mut count = 0;
+    let mut idx = 0;
+    'outer: while idx < text.len() {
+        if text[idx] == b'$' {
+            for (old, new) in renames {
+                let old = old.as_bytes();
+                let end = idx + old.len();
+                if text[idx..].starts_with(old)
+                    && text.get(end).map(|xs| !continues_key(*xs)).unwrap_or(true)
+                {
+                    output.extend_from_slice(new.as_bytes());
+                    count += 1;
+                    idx = end;
+                    continue 'outer;
+                }
+            }
+        }
+        output.push(text[idx]);
+        idx += 1;
+    }
+    (output, count)
+}
+
+fn replace_keys_in_str(text: &str, renames: &[(String, String)]) -> (String, usize) {
+    let (bytes, count) = replace_keys(text.as_bytes(), renames);
+    // Keys start with an ASCII `$` and end before a non-key byte, so replacing
+    // them never splits a utf-8 sequence.
+    (String::from_utf8_lossy(&bytes).to_string(), count)
+}
+
+/// True if the file looks like binary data rather than text.
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|xs| *xs == 0)
+}
+
+/// Check that a key is something the game will treat as a translation key.
+pub fn validate_key(key: &str) -> Result<()> {
+    if !key.starts_with('$') || key.len() < 2 {
+        return Err(eyre::eyre!(
+            "`{key}` is not a translation key; keys start with $"
+        ));
+    }
+    if key[1..]
+        .chars()
+        .any(|xs| xs.is_whitespace() || xs == '$' || xs == '{' || xs == '}')
+    {
+        return Err(eyre::eyre!(
+            "`{key}` can't be a translation key; keys can't contain whitespace, $, or braces"
+        ));
+    }
+    Ok(())
+}
+
+/// Apply a set of renames to every source file and translation file in the mod,
+/// returning the files changed. With `dry_run`, nothing is written.
+pub fn apply_renames(
+    moddir: &mut ModDirectory,
+    renames: &[(String, String)],
+    dry_run: bool,
+) -> Result<Vec<RenamedFile>> {
+    let mut touched: Vec<RenamedFile> = Vec::new();
+    let mut pending: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+
+    for fpath in moddir.source_files() {
+        let extension = fpath
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        if ARCHIVE_EXTENSIONS.contains(&extension.as_str()) {
+            continue;
+        }
+        let mut bytes: Vec<u8> = Vec::new();
+        std::fs::File::open(&fpath)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .context(format!("reading {}", fpath.display()))?;
+        let (replaced, count) = replace_keys(&bytes, renames);
+        if count == 0 {
+            continue;
+        }
+        let display = moddir.relative_display(&fpath);
+        if is_binary(&bytes) || COMPILED_EXTENSIONS.contains(&extension.as_str()) {
+            log::warn!(
+                "{} uses a renamed key but isn't a text file; rebuild or edit it by hand.",
+                display.bold()
+            );
+            continue;
+        }
+        touched.push(RenamedFile {
+            display,
+            replacements: count,
+        });
+        pending.push((fpath, replaced));
+    }
+
+    let mut trfiles: Vec<_> = moddir.translation_files()?.into_values().collect();
+    trfiles.sort_by(|a, b| a.path().cmp(b.path()));
+    let mut pending_translations = Vec::new();
+    for mut trfile in trfiles {
+        trfile.load_translations()?;
+        let mut count = 0;
+        let lines: Vec<(String, String)> = trfile
+            .lines()
+            .iter()
+            .map(|(key, value)| {
+                let (key, in_key) = replace_keys_in_str(key, renames);
+                let (value, in_value) = replace_keys_in_str(value, renames);
+                count += in_key + in_value;
+                (key, value)
+            })
+            .collect();
+        if count == 0 {
+            continue;
+        }
+        trfile.set_lines(lines);
+        touched.push(RenamedFile {
+            display: moddir.relative_display(trfile.path()),
+            replacements: count,
+        });
+        pending_translations.push(trfile);
+    }
+
+    if dry_run {
+        return Ok(touched);
+    }
+    for (fpath, replaced) in pending {
+        std::fs::write(&fpath, replaced).context(format!("writing {}", fpath.display()))?;
+    }
+    for trfile in pending_translations {
+        trfile.write()?;
+    }
+    Ok(touched)
+}
+
+/// Print the files a rename touched.
+pub fn report_renamed(touched: &[RenamedFile]) {
+    let mut table = Table::new();
+    table
+        .load_preset(NOTHING)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    for file in touched {
+        table.add_row(vec![
+            Cell::new(file.display.as_str())
+                .fg(Color::Blue)
+                .add_attribute(Attribute::Bold),
+            Cell::new(file.replacements),
+        ]);
+    }
+    log::warn!("{table}");
+}
+
+/// Rename a translation key across the whole mod.
+pub fn rename_key(args: &Args, old: &str, new: &str, dry_run: bool) -> Result<bool, Report> {
+    validate_key(old)?;
+    validate_key(new)?;
+    if old == new {
+        return Err(eyre::eyre!("The old and new names are the same."));
+    }
+
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let mut in_use = moddir.all_needed_translations()?.iter().any(|xs| xs == new);
+    for trfile in moddir.translation_files()?.values_mut() {
+        in_use |= trfile.translations()?.contains_key(new);
+    }
+    if in_use {
+        return Err(eyre::eyre!(
+            "{new} is already in use; renaming {old} to it would merge two keys."
+        ));
+    }
+
+    let renames = vec![(old.to_string(), new.to_string())];
+    let touched = apply_renames(&mut moddir, &renames, dry_run)?;
+    if touched.is_empty() {
+        log::warn!("Found no uses of {} to rename.", old.bold());
+        return Ok(false);
+    }
+
+    report_renamed(&touched);
+    let total: usize = touched.iter().map(|xs| xs.replacements).sum();
+    log::info!(
+        "{} {} to {}: {} replacements in {} files.",
+        if dry_run { "Would rename" } else { "Renamed" },
+        old.bold(),
+        new.bold(),
+        total.bold(),
+        touched.len().bold()
+    );
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn replace_keys_rewrites_only_whole_keys() {
+        let renames = renames(&[("$MyMod_A", "$MyMod_New")]);
+        let (text, count) = replace_keys(
+            b"$MyMod_A $MyMod_AB $MyMod_A{} \"$MyMod_A\" $MyMod_A_B $MyMod_A",
+            &renames,
+        );
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "$MyMod_New $MyMod_AB $MyMod_New{} \"$MyMod_New\" $MyMod_A_B $MyMod_New"
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn replace_keys_leaves_other_bytes_alone() {
+        let renames = renames(&[("$MyMod_A", "$MyMod_New")]);
+        let (text, count) = replace_keys("$MyMod_Aß ß$MyMod_A".as_bytes(), &renames);
+        assert_eq!(String::from_utf8(text).unwrap(), "$MyMod_Aß ß$MyMod_New");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn validate_key_rejects_names_the_game_wont_treat_as_keys() {
+        assert!(validate_key("$MyMod_New").is_ok());
+        for bad in ["MyMod_New", "$", "$MyMod New", "$MyMod$New", "$MyMod{}", ""] {
+            assert!(validate_key(bad).is_err(), "{bad} should be rejected");
+        }
+    }
+
+    fn fixture() -> ModFixture {
+        ModFixture::new(&["$MyMod_A", "$MyMod_B"])
+            .file("data/Scripts/Source/MyMod.psc", "string s = \"$MyMod_A\"\n")
+            .file("data/Scripts/MyMod.pex", "\u{0}compiled $MyMod_A\u{0}")
+            .translation(
+                "MyMod_english.txt",
+                &["$MyMod_A\tA", "$MyMod_B\tSee $MyMod_A{}"],
+            )
+    }
+
+    #[test]
+    fn apply_renames_changes_nothing_in_a_dry_run() {
+        let fixture = fixture();
+        let before = std::fs::read(fixture.translations_dir().join("MyMod_english.txt")).unwrap();
+        let renames = renames(&[("$MyMod_A", "$MyMod_New")]);
+        let touched = apply_renames(&mut fixture.moddir(), &renames, true).unwrap();
+
+        let found: Vec<(&str, usize)> = touched
+            .iter()
+            .map(|xs| (xs.display.as_str(), xs.replacements))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("data/Scripts/Source/MyMod.psc", 1),
+                ("data/mcm/config/MyMod/config.json", 1),
+                ("data/Interface/Translations/MyMod_english.txt", 2),
+            ]
+        );
+        let after = std::fs::read(fixture.translations_dir().join("MyMod_english.txt")).unwrap();
+        assert_eq!(before, after);
+        let script = std::fs::read_to_string(fixture.path().join("data/Scripts/Source/MyMod.psc"));
+        assert_eq!(script.unwrap(), "string s = \"$MyMod_A\"\n");
+    }
+
+    #[test]
+    fn apply_renames_skips_binary_files() {
+        let fixture = fixture();
+        let renames = renames(&[("$MyMod_A", "$MyMod_New")]);
+        apply_renames(&mut fixture.moddir(), &renames, false).unwrap();
+
+        let script = std::fs::read_to_string(fixture.path().join("data/Scripts/Source/MyMod.psc"));
+        assert_eq!(script.unwrap(), "string s = \"$MyMod_New\"\n");
+        let compiled = std::fs::read(fixture.path().join("data/Scripts/MyMod.pex")).unwrap();
+        assert_eq!(compiled, b"\0compiled $MyMod_A\0");
+        let mut english = fixture
+            .moddir()
+            .translation_file_for("english")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            english
+                .translations()
+                .unwrap()
+                .get("$MyMod_B")
+                .map(|xs| xs.as_str()),
+            Some("See $MyMod_New{}")
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Check results that can be saved as a baseline and compared against later,
+//! so mods with a long list of known problems can fail only on new ones.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::Path;
+
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The problems found for every language checked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckResults {
+    pub languages: BTreeMap<String, LanguageResults>,
+}
+
+/// The problems found for a single language.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageResults {
+    pub missing: Vec<String>,
+    pub unused: Vec<String>,
+}
+
+/// How one language's problems changed relative to a baseline.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageDiff {
+    pub new_missing: Vec<String>,
+    pub fixed_missing: Vec<String>,
+    pub new_unused: Vec<String>,
+    pub fixed_unused: Vec<String>,
+}
+
+impl LanguageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new_missing.is_empty()
+            && self.fixed_missing.is_empty()
+            && self.new_unused.is_empty()
+            && self.fixed_unused.is_empty()
+    }
+}
+
+impl CheckResults {
+    pub fn record(&mut self, language: &str, missing: &[String], unused: &[String]) {
+        self.languages.insert(
+            language.to_string(),
+            LanguageResults {
+                missing: missing.to_vec(),
+                unused: unused.to_vec(),
+            },
+        );
+    }
+
+    pub fn load(fpath: &Path) -> Result<Self> {
+        let file =
+            File::open(fpath).context(format!("opening the baseline {}", fpath.display()))?;
+        let rdr = std::io::BufReader::new(file);
+        let results: CheckResults = serde_json::from_reader(rdr)
+            .context(format!("parsing the baseline {}", fpath.display()))?;
+        Ok(results)
+    }
+
+    pub fn write(&self, fpath: &Path) -> Result<()> {
+        let file =
+            File::create(fpath).context(format!("creating the baseline {}", fpath.display()))?;
+        serde_json::to_writer_pretty(file, self)
+            .context(format!("writing the baseline {}", fpath.display()))?;
+        Ok(())
+    }
+
+    /// Compare these results with an earlier baseline, language by language.
+    /// Only the languages checked this time are compared, so checking one
+    /// language against a baseline of all of them works. Languages with no
+    /// changes are left out.
+    pub fn diff(&self, baseline: &CheckResults) -> BTreeMap<String, LanguageDiff> {
+        let empty = LanguageResults::default();
+        self.languages
+            .iter()
+            .filter_map(|(language, now)| {
+                let then = baseline.languages.get(language).unwrap_or(&empty);
+                let diff = LanguageDiff {
+                    new_missing: difference(&now.missing, &then.missing),
+                    fixed_missing: difference(&then.missing, &now.missing),
+                    new_unused: difference(&now.unused, &then.unused),
+                    fixed_unused: difference(&then.unused, &now.unused),
+                };
+                if diff.is_empty() {
+                    None
+                } else {
+                    Some((language.clone(), diff))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Items in the first list that aren't in the second, sorted.
+fn difference(left: &[String], right: &[String]) -> Vec<String> {
+    let right: BTreeSet<&String> = right.iter().collect();
+    let result: BTreeSet<&String> = left.iter().filter(|xs| !right.contains(xs)).collect();
+    result.into_iter().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(list: &[&str]) -> Vec<String> {
+        list.iter().map(|xs| xs.to_string()).collect()
+    }
+
+    #[test]
+    fn diff_reports_new_and_fixed_problems() {
+        let mut baseline = CheckResults::default();
+        baseline.record("german", &keys(&["$A", "$B"]), &keys(&["$Old"]));
+        let mut now = CheckResults::default();
+        now.record("german", &keys(&["$B", "$C"]), &keys(&["$Old"]));
+
+        let diffs = now.diff(&baseline);
+        let german = &diffs["german"];
+        assert_eq!(german.new_missing, keys(&["$C"]));
+        assert_eq!(german.fixed_missing, keys(&["$A"]));
+        assert!(german.new_unused.is_empty());
+        assert!(german.fixed_unused.is_empty());
+    }
+
+    #[test]
+    fn diff_leaves_out_unchanged_languages() {
+        let mut baseline = CheckResults::default();
+        baseline.record("german", &keys(&["$A"]), &[]);
+        let now = baseline.clone();
+        assert!(now.diff(&baseline).is_empty());
+    }
+
+    #[test]
+    fn diff_compares_only_the_languages_checked() {
+        let mut baseline = CheckResults::default();
+        baseline.record("german", &keys(&["$A"]), &[]);
+        baseline.record("french", &keys(&["$A", "$B"]), &[]);
+        let mut now = CheckResults::default();
+        now.record("german", &keys(&["$A"]), &[]);
+
+        // French wasn't checked this time, so its problems weren't fixed.
+        assert!(now.diff(&baseline).is_empty());
+    }
+
+    #[test]
+    fn results_round_trip_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = dir.path().join("baseline.json");
+        let mut results = CheckResults::default();
+        results.record("german", &keys(&["$A"]), &keys(&["$B"]));
+        results.write(&fpath).unwrap();
+
+        let loaded = CheckResults::load(&fpath).unwrap();
+        assert_eq!(loaded.languages["german"].missing, keys(&["$A"]));
+        assert_eq!(loaded.languages["german"].unused, keys(&["$B"]));
+    }
+}
@@ -1,296 +1,150 @@
-//! The list of translation keys that SkyUI itself provides.
-//! These are not translations mods need to provide, so we need
-//! to include them when looking for all available translations.
+//! The translation keys that SkyUI and other frameworks provide themselves.
+//! Mods can request these without providing translations for them, so we
+//! leave them out when looking for missing translations.
 
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-use once_cell::sync::Lazy;
+use eyre::{Context, Result};
+use walkdir::WalkDir;
 
-pub fn filter_skyui_translations(input: &[String]) -> Vec<String> {
-    input
-        .iter()
-        .filter_map(|xs| {
-            if !SKYUI_KEYS.contains(xs) {
-                Some(xs.clone())
-            } else {
-                None
-            }
+use crate::{ProjectConfig, Translation};
+
+/// The list of SkyUI's keys bundled with this tool.
+const BUNDLED_SKYUI_KEYS: &str = include_str!("../keysets/skyui.txt");
+
+/// Read a bundled key list: one key per line, with `;` comments and blank lines skipped.
+fn keys_from_list(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(|xs| xs.trim())
+        .filter(|xs| !xs.is_empty() && !xs.starts_with(';'))
+        .map(|xs| xs.to_string())
+}
+
+/// The bundled list of SkyUI's keys.
+pub fn bundled_skyui_keys() -> HashSet<String> {
+    HashSet::from_iter(keys_from_list(BUNDLED_SKYUI_KEYS))
+}
+
+/// Find SkyUI's english translation file in a SkyUI install. Every language's
+/// file has the same keys, and every install has the english one.
+fn skyui_translation_file(path: &Path) -> Result<PathBuf> {
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let mut found: Vec<PathBuf> = WalkDir::new(path)
+        .max_depth(4)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            let parent = e
+                .path()
+                .parent()
+                .and_then(|xs| xs.file_name())
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_lowercase();
+            parent == "translations" && name.starts_with("skyui") && name.ends_with("_english.txt")
         })
-        .collect()
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    found.sort();
+    found.into_iter().next().ok_or_else(|| {
+        eyre::eyre!(
+            "Can't find SkyUI's english translation file in {}; set skyui_path to the SkyUI mod folder or to the file itself.",
+            path.display()
+        )
+    })
+}
+
+/// The keys SkyUI provides, read from a SkyUI install.
+pub fn installed_skyui_keys(path: &Path) -> Result<HashSet<String>> {
+    let fpath = skyui_translation_file(path)?;
+    let mut trfile = Translation::new(fpath.clone(), "english");
+    let provided = trfile
+        .provided_translations()
+        .context(format!("reading SkyUI's keys from {}", fpath.display()))?;
+    log::debug!(
+        "Read {} SkyUI keys from {}",
+        provided.len(),
+        fpath.display()
+    );
+    Ok(HashSet::from_iter(provided))
 }
 
-pub static SKYUI_KEYS: Lazy<HashSet<String>> = Lazy::new(|| {
-    HashSet::from_iter(
-        vec![
-            "$3D",
-            "$Active",
-            "$Advanced",
-            "$Aetherium",
-            "$AID",
-            "$Align",
-            "$ALL",
-            "$Alteration",
-            "$AMMO",
-            "$Amulet",
-            "$ARM",
-            "$Armor",
-            "$Arrow",
-            "$Artifact",
-            "$B.ARM",
-            "$B.DAM",
-            "$Barter",
-            "$BASE",
-            "$BASE",
-            "$Battleaxe",
-            "$Body",
-            "$Bolt",
-            "$Bonemold",
-            "$Book",
-            "$Bottom",
-            "$Bow",
-            "$Brotherhood",
-            "$Calves",
-            "$Category",
-            "$Center",
-            "$Chitin",
-            "$Circlet",
-            "$CLASS",
-            "$Claw",
-            "$Clothing",
-            "$Clutter",
-            "$Column",
-            "$Common",
-            "$Confirm",
-            "$Container",
-            "$COOLDOWN",
-            "$COVR",
-            "$Crafting",
-            "$Crossbow",
-            "$Daedric",
-            "$Dagger",
-            "$DAM",
-            "$Dawnguard",
-            "$Deathbrand",
-            "$Default",
-            "$Defaults",
-            "$Destruction",
-            "$Disable",
-            "$Dragonbone",
-            "$Dragonplate",
-            "$Dragonscale",
-            "$Draugr",
-            "$Drink",
-            "$DUR",
-            "$DURATION",
-            "$Dwarven",
-            "$Ears",
-            "$Ebony",
-            "$EFFECT",
-            "$Elven",
-            "$Enabled",
-            "$ENCHANTED",
-            "$Equip",
-            "$EQUIPPED",
-            "$EXPPROT",
-            "$EXPPROTLOW",
-            "$F.WRMT",
-            "$Falmer",
-            "$Favorite",
-            "$FAVORITE",
-            "$Favorites",
-            "$Feet",
-            "$FILTER",
-            "$Find",
-            "$Firewood",
-            "$FIRST",
-            "$Font",
-            "$Food",
-            "$Forearms",
-            "$Forsworn",
-            "$FORTIFY",
-            "$FSForget",
-            "$Fur",
-            "$GEAR",
-            "$Gem",
-            "$General",
-            "$Gift",
-            "$Glass",
-            "$Gold",
-            "$Grand",
-            "$Greater",
-            "$Greater",
-            "$Greater",
-            "$Greatsword",
-            "$Group",
-            "$GROUP",
-            "$Gun",
-            "$Halberd",
-            "$Hands",
-            "$Head",
-            "$Heavy",
-            "$Hide",
-            "$HNGR",
-            "$Horizontal",
-            "$House",
-            "$HUNGER",
-            "$Hunter",
-            "$Icon",
-            "$IJBag",
-            "$IJBracelet",
-            "$IJChoker",
-            "$IJCrown",
-            "$IJEar",
-            "$IJEarrings",
-            "$IJNecklace",
-            "$IJTorc",
-            "$Illusion",
-            "$Imperial",
-            "$Ingot",
-            "$Ingredient",
-            "$Input",
-            "$Inventory",
-            "$Iron",
-            "$Item",
-            "$Javelin",
-            "$Jewelry",
-            "$Katana",
-            "$Key",
-            "$Leather",
-            "$Left",
-            "$Lesser",
-            "$Lesser",
-            "$Light",
-            "$Lockpick",
-            "$Mace",
-            "$MAG",
-            "$Magic",
-            "$Magical",
-            "$MAGNITUDE",
-            "$Map",
-            "$Mask",
-            "$MAT",
-            "$MATERIAL",
-            "$MCMMenuName",
-            "$Melee",
-            "$Minimum",
-            "$Misc",
-            "$MOD",
-            "$Morag",
-            "$Next",
-            "$Nightingale",
-            "$None",
-            "$Nordic",
-            "$Note",
-            "$Off",
-            "$On",
-            "$Open",
-            "$Orcish",
-            "$Order",
-            "$Ore",
-            "$Orientation",
-            "$Other",
-            "$Petty",
-            "$Pick",
-            "$Pickaxe",
-            "$Pike",
-            "$Poison",
-            "$Potion",
-            "$Preferences",
-            "$Previous",
-            "$Quantity",
-            "$Quarterstaff",
-            "$R.COLD",
-            "$RAINPROT",
-            "$RAINPROTLOW",
-            "$Rapier",
-            "$RCH",
-            "$REACH",
-            "$Ready",
-            "$Recipe",
-            "$Remains",
-            "$Remap",
-            "$Restoration",
-            "$RESTORE",
-            "$Right",
-            "$Ring",
-            "$Save",
-            "$Scale",
-            "$Scaled",
-            "$SCHOOL",
-            "$Scroll",
-            "$Scythe",
-            "$Search",
-            "$SECOND",
-            "$Select",
-            "$Set",
-            "$Shield",
-            "$SHOUTS",
-            "$Show",
-            "$Silver",
-            "$SKI_INFO1{}",
-            "$SKI_INFO2{}",
-            "$SKI_INFO3{}",
-            "$SKI_INFO4{}",
-            "$SKI_INFO5{}",
-            "$SKI_INFO6",
-            "$SKI_INFO7{}",
-            "$SKI_INFO8{}",
-            "$SKI_INFO9{}",
-            "$SKI_MSG1",
-            "$SKI_MSG2{}",
-            "$SKILL",
-            "$SLOT",
-            "$Soul",
-            "$SOURCE",
-            "$SPD",
-            "$Spear",
-            "$SPEED",
-            "$Spell",
-            "$SPELL",
-            "$Staff",
-            "$STAGGER",
-            "$Stalhrim",
-            "$Steel",
-            "$STGR",
-            "$STOLEN",
-            "$Stormcloak",
-            "$Strips",
-            "$Studded",
-            "$SWF",
-            "$Switch",
-            "$Sword",
-            "$T.WGT",
-            "$Tail",
-            "$THIRD",
-            "$THIRST",
-            "$TIME",
-            "$Toggle",
-            "$Tool",
-            "$Top",
-            "$Torch",
-            "$TOTAL",
-            "$Toy",
-            "$TRST",
-            "$TYPE",
-            "$Unequip",
-            "$Ungroup",
-            "$Unmap",
-            "$V/W",
-            "$VAL",
-            "$VALUE/WEIGHT",
-            "$Vampire",
-            "$Vertical",
-            "$War",
-            "$Warhammer",
-            "$WARMTH",
-            "$Weapon",
-            "$WEAPONS",
-            "$WGT",
-            "$Whip",
-            "$Wood",
-            "$WRMT",
-        ]
-        .iter()
-        .map(|xs| xs.to_string()),
-    )
-});
+/// Every key a mod gets for free: SkyUI's keys, from the SkyUI install named
+/// in the project config or else the bundled list unless it's turned off,
+/// the keys in any framework translation files named in the project config,
+/// and any keys listed in the config directly.
+pub fn builtin_keys(config: &ProjectConfig, modpath: &Path) -> Result<HashSet<String>> {
+    let mut keys = match config.skyui_path {
+        Some(ref path) => installed_skyui_keys(&modpath.join(path))?,
+        None if config.bundled_skyui_keys => bundled_skyui_keys(),
+        None => HashSet::new(),
+    };
+    for fpath in config.builtin_key_files.iter() {
+        let fpath = modpath.join(fpath);
+        let mut trfile = Translation::new(fpath.clone(), "builtin");
+        let provided = trfile
+            .provided_translations()
+            .context(format!("reading built-in keys from {}", fpath.display()))?;
+        keys.extend(provided);
+    }
+    keys.extend(config.builtin_keys.iter().cloned());
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_keys_can_be_turned_off() {
+        let keys = builtin_keys(&ProjectConfig::default(), Path::new(".")).unwrap();
+        assert!(keys.contains("$Enabled"));
+        assert!(!keys.iter().any(|xs| xs.starts_with(';')));
+
+        let config = ProjectConfig {
+            bundled_skyui_keys: false,
+            builtin_keys: vec!["$MCM_Example".to_string()],
+            ..Default::default()
+        };
+        let keys = builtin_keys(&config, Path::new(".")).unwrap();
+        assert_eq!(keys, HashSet::from(["$MCM_Example".to_string()]));
+    }
+
+    #[test]
+    fn keys_from_a_skyui_install_replace_the_bundled_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let translations = dir.path().join("SkyUI_SE/Interface/Translations");
+        std::fs::create_dir_all(&translations).unwrap();
+        let mut trfile = Translation::new(translations.join("SkyUI_SE_english.txt"), "english");
+        trfile.set_lines(vec![
+            ("$SKI_Installed".to_string(), "Installed".to_string()),
+            ("$Enabled".to_string(), "Enabled".to_string()),
+        ]);
+        trfile.write().unwrap();
+
+        let config = ProjectConfig {
+            skyui_path: Some(PathBuf::from("SkyUI_SE")),
+            builtin_keys: vec!["$MCM_Example".to_string()],
+            ..Default::default()
+        };
+        let keys = builtin_keys(&config, dir.path()).unwrap();
+        let mut keys: Vec<String> = keys.into_iter().collect();
+        keys.sort();
+        assert_eq!(keys, ["$Enabled", "$MCM_Example", "$SKI_Installed"]);
+    }
+
+    #[test]
+    fn a_skyui_install_without_translations_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ProjectConfig {
+            skyui_path: Some(PathBuf::from(".")),
+            ..Default::default()
+        };
+        assert!(builtin_keys(&config, dir.path()).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! A single CSV sheet of every key in every language, for volunteers who
+//! translate in a spreadsheet. The sheet has a key column, a location column,
+//! and a column for each language, source language first.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result};
+
+use crate::{language_for_code, ExchangeSet, ImportedTranslations};
+
+/// The column holding each row's translation key.
+const KEY_COLUMN: &str = "key";
+/// The column holding where each key is requested, for context.
+const LOCATION_COLUMN: &str = "location";
+
+/// Write the sheet. It starts with a byte order mark, so spreadsheet programs
+/// read it as UTF-8.
+pub fn write_csv_file(set: &ExchangeSet, output: &Path) -> Result<Vec<PathBuf>> {
+    let fpath = output.join(format!("{}.csv", set.name));
+    let mut writer = csv::Writer::from_writer("\u{feff}".as_bytes().to_vec());
+
+    let mut header = vec![KEY_COLUMN, LOCATION_COLUMN, set.source_language.as_str()];
+    header.extend(set.languages.iter().map(|xs| xs.as_str()));
+    writer.write_record(&header)?;
+    for entry in set.entries.iter() {
+        // Spreadsheets show line breaks in a cell, which reads better than spaces.
+        let locations = entry.locations.join("\n");
+        let mut record = vec![
+            entry.key.as_str(),
+            locations.as_str(),
+            entry.source.as_deref().unwrap_or_default(),
+        ];
+        record.extend(set.languages.iter().map(|language| {
+            entry
+                .translations
+                .get(language)
+                .map(|xs| xs.as_str())
+                .unwrap_or_default()
+        }));
+        writer.write_record(&record)?;
+    }
+
+    let bytes = writer.into_inner()?;
+    std::fs::write(&fpath, bytes).context(format!("writing {}", fpath.display()))?;
+    Ok(vec![fpath])
+}
+
+/// Read every language column but the source language's from a sheet. The
+/// sheet may separate its cells with commas, semicolons, or tabs, since
+/// spreadsheet programs in some locales don't use commas.
+pub fn read_csv(
+    fpath: &Path,
+    source_language: &str,
+    languages: &[String],
+) -> Result<Vec<ImportedTranslations>> {
+    let text = std::fs::read_to_string(fpath).context(format!("reading {}", fpath.display()))?;
+    let text = text.trim_start_matches('\u{feff}');
+    let first_line = text.lines().next().unwrap_or_default();
+    let delimiter = [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|xs| first_line.matches(*xs as char).count())
+        .unwrap_or(b',');
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let header: Vec<String> = reader
+        .headers()
+        .context(format!("reading the header row of {}", fpath.display()))?
+        .iter()
+        .map(|xs| xs.trim().to_string())
+        .collect();
+    let Some(key_column) = header
+        .iter()
+        .position(|xs| xs.eq_ignore_ascii_case(KEY_COLUMN))
+    else {
+        return Err(eyre::eyre!(
+            "{} has no '{KEY_COLUMN}' column in its header row.",
+            fpath.display()
+        ));
+    };
+
+    // Every column named after a language, by a Skyrim name, a language code,
+    // or the name of one of the mod's translation files.
+    let mut imported: Vec<(usize, ImportedTranslations)> = header
+        .iter()
+        .enumerate()
+        .filter(|(idx, _name)| *idx != key_column)
+        .filter(|(_idx, name)| !name.eq_ignore_ascii_case(LOCATION_COLUMN))
+        .filter_map(|(idx, name)| {
+            let language = language_for_code(name).or_else(|| {
+                languages
+                    .iter()
+                    .find(|xs| xs.eq_ignore_ascii_case(name))
+                    .cloned()
+            })?;
+            (language != source_language).then_some((
+                idx,
+                ImportedTranslations {
+                    language,
+                    values: Vec::new(),
+                },
+            ))
+        })
+        .collect();
+    if imported.is_empty() {
+        return Err(eyre::eyre!(
+            "{} has no columns named after a language other than {source_language}.",
+            fpath.display()
+        ));
+    }
+
+    for (row, record) in reader.records().enumerate() {
+        let record = record.context(format!("reading row {} of {}", row + 2, fpath.display()))?;
+        let key = record.get(key_column).unwrap_or_default().trim();
+        if key.is_empty() {
+            continue;
+        }
+        for (column, translations) in imported.iter_mut() {
+            let value = record.get(*column).unwrap_or_default().trim();
+            if !value.is_empty() {
+                translations
+                    .values
+                    .push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+    // Columns nobody filled in yet don't need a translation file to import into.
+    Ok(imported
+        .into_iter()
+        .map(|(_column, xs)| xs)
+        .filter(|xs| !xs.values.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Result<Vec<ImportedTranslations>> {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = dir.path().join("MyMod.csv");
+        std::fs::write(&fpath, text).unwrap();
+        read_csv(
+            &fpath,
+            "english",
+            &["german".to_string(), "klingon".to_string()],
+        )
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn reads_every_language_but_the_source() {
+        let imported =
+            read("key,location,english,german,fr\n$MyMod_Title,config.json:3,Title,Titel,Titre\n")
+                .unwrap();
+        let languages: Vec<&str> = imported.iter().map(|xs| xs.language.as_str()).collect();
+        assert_eq!(languages, ["german", "french"]);
+        assert_eq!(imported[0].values, [pair("$MyMod_Title", "Titel")]);
+        assert_eq!(imported[1].values, [pair("$MyMod_Title", "Titre")]);
+    }
+
+    #[test]
+    fn detects_the_delimiter() {
+        for text in [
+            "key;english;german\n$MyMod_Title;Title, long;Titel, lang\n",
+            "key\tenglish\tgerman\n$MyMod_Title\tTitle, long\tTitel, lang\n",
+        ] {
+            let imported = read(text).unwrap();
+            assert_eq!(imported[0].values, [pair("$MyMod_Title", "Titel, lang")]);
+        }
+    }
+
+    #[test]
+    fn skips_the_byte_order_mark() {
+        let imported = read("\u{feff}key,german\n$MyMod_Title,Titel\n").unwrap();
+        assert_eq!(imported[0].values, [pair("$MyMod_Title", "Titel")]);
+    }
+
+    #[test]
+    fn skips_empty_cells_and_columns() {
+        let imported = read(
+            "key,english,german,klingon,notes\n$MyMod_Title,Title,,,\n$MyMod_Help,Help,Hilfe,,check this\n,,Waise,,\n",
+        )
+        .unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].language, "german");
+        assert_eq!(imported[0].values, [pair("$MyMod_Help", "Hilfe")]);
+    }
+
+    #[test]
+    fn needs_a_key_column_and_a_language_column() {
+        assert!(read("id,german\n$MyMod_Title,Titel\n").is_err());
+        assert!(read("key,english,notes\n$MyMod_Title,Title,x\n").is_err());
+    }
+}
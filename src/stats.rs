@@ -0,0 +1,221 @@
+//! Translation completion statistics, for estimating how much work is left
+//! for translators and for feeding progress badges.
+
+use std::collections::{BTreeMap, HashSet};
+
+use comfy_table::presets::UTF8_BORDERS_ONLY;
+use comfy_table::*;
+use eyre::{Context, Report, Result};
+use serde::Serialize;
+
+use crate::{
+    completion_percent, confirmed_unused, is_stub, untranslated_values, Args, ModDirectory,
+    Untranslated,
+};
+
+/// Completion statistics for a single language.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LanguageStats {
+    /// Keys the mod needs a translation for.
+    pub required: usize,
+    /// Required keys the language file provides.
+    pub present: usize,
+    /// Required keys the language file lacks.
+    pub missing: usize,
+    /// Required keys whose value is still the stub written by `update`.
+    pub stubbed: usize,
+    /// Required keys whose value is the same as the source language's.
+    pub identical: usize,
+    /// Keys provided but never requested.
+    pub unused: usize,
+    /// Percentage of required keys with a real translation.
+    pub complete: f64,
+    /// Words of source language text still needing translation.
+    pub untranslated_words: usize,
+    /// Characters of source language text still needing translation.
+    pub untranslated_chars: usize,
+}
+
+/// Gather statistics for every language this mod has a translation file for.
+pub fn gather_stats(moddir: &mut ModDirectory) -> Result<BTreeMap<String, LanguageStats>> {
+    let requested = moddir
+        .all_needed_translations()
+        .context("Finding all requested translations")?;
+    let requested: HashSet<String> = HashSet::from_iter(requested);
+    let required: HashSet<String> = HashSet::from_iter(moddir.required_translations()?);
+
+    let source_language = moddir.config().source_language.clone();
+    let source_values = match moddir.translation_file_for(source_language.as_str())? {
+        Some(mut source) => source.translations()?,
+        None => Default::default(),
+    };
+
+    let mut result = BTreeMap::new();
+    for (language, mut trfile) in moddir.translation_files()? {
+        let translations = trfile.translations()?;
+        let untranslated: Vec<(String, Untranslated)> =
+            untranslated_values(&source_values, &translations, language == source_language)
+                .into_iter()
+                .filter(|(key, _why)| required.contains(key))
+                .collect();
+        let missing: Vec<&String> = required
+            .iter()
+            .filter(|key| !translations.contains_key(*key))
+            .collect();
+        let unused = confirmed_unused(moddir, &requested, &mut trfile)?;
+
+        let needing_work = missing
+            .iter()
+            .copied()
+            .chain(untranslated.iter().map(|(key, _why)| key));
+        let (words, chars) = needing_work.fold((0, 0), |(words, chars), key| {
+            // A source value that is still a stub has no text anyone will translate.
+            let text = source_values
+                .get(key)
+                .map(|xs| xs.as_str())
+                .filter(|xs| !is_stub(key, xs))
+                .unwrap_or("");
+            (
+                words + text.split_whitespace().count(),
+                chars + text.chars().count(),
+            )
+        });
+
+        let stubbed = untranslated
+            .iter()
+            .filter(|(_key, why)| *why == Untranslated::Stub)
+            .count();
+        let stats = LanguageStats {
+            required: required.len(),
+            present: required.len() - missing.len(),
+            missing: missing.len(),
+            stubbed,
+            identical: untranslated.len() - stubbed,
+            unused: unused.len(),
+            complete: completion_percent(required.len(), missing.len(), untranslated.len()),
+            untranslated_words: words,
+            untranslated_chars: chars,
+        };
+        result.insert(language, stats);
+    }
+
+    Ok(result)
+}
+
+/// Print translation statistics for every language, as a table or as json.
+pub fn stats(args: &Args, json: bool) -> Result<bool, Report> {
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let stats = gather_stats(&mut moddir)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(true);
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_BORDERS_ONLY)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "language",
+            "required",
+            "present",
+            "missing",
+            "stubbed",
+            "identical",
+            "unused",
+            "complete",
+            "words left",
+            "chars left",
+        ]);
+    for (language, stats) in stats.iter() {
+        let complete_color = if stats.complete >= 100.0 {
+            Color::Green
+        } else if stats.complete >= 50.0 {
+            Color::Yellow
+        } else {
+            Color::Red
+        };
+        table.add_row(vec![
+            Cell::new(language)
+                .fg(Color::Blue)
+                .add_attribute(Attribute::Bold),
+            Cell::new(stats.required),
+            Cell::new(stats.present),
+            Cell::new(stats.missing),
+            Cell::new(stats.stubbed),
+            Cell::new(stats.identical),
+            Cell::new(stats.unused),
+            Cell::new(format!("{:.0}%", stats.complete.floor())).fg(complete_color),
+            Cell::new(stats.untranslated_words),
+            Cell::new(stats.untranslated_chars),
+        ]);
+    }
+    log::warn!("{table}");
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn counts_missing_untranslated_and_unused_keys() {
+        let fixture = ModFixture::new(&["$MyMod_Title", "$MyMod_Help", "$MyMod_Stub"])
+            .translation(
+                "MyMod_english.txt",
+                &[
+                    "$MyMod_Title\tTitle",
+                    "$MyMod_Help\tSome help text",
+                    "$MyMod_Stub\tStub",
+                ],
+            )
+            .translation(
+                "MyMod_german.txt",
+                &[
+                    "$MyMod_Title\tTitel",
+                    "$MyMod_Stub\ttranslation for MyMod_Stub",
+                    "$MyMod_Old\tAlt",
+                ],
+            );
+        let stats = gather_stats(&mut fixture.moddir()).unwrap();
+        let german = &stats["german"];
+        assert_eq!(german.required, 3);
+        assert_eq!(german.missing, 1);
+        assert_eq!(german.stubbed, 1);
+        assert_eq!(german.unused, 1);
+        assert_eq!(german.untranslated_words, 4);
+    }
+
+    #[test]
+    fn stubs_in_the_source_language_are_not_words_left_to_translate() {
+        let fixture = ModFixture::new(&["$MyMod_Title", "$MyMod_Help"])
+            .translation(
+                "MyMod_english.txt",
+                &[
+                    "$MyMod_Title\tTitle",
+                    "$MyMod_Help\ttranslation for MyMod_Help",
+                ],
+            )
+            .translation("MyMod_german.txt", &[]);
+        let stats = gather_stats(&mut fixture.moddir()).unwrap();
+        assert_eq!(stats["german"].untranslated_words, 1);
+        assert_eq!(stats["german"].untranslated_chars, 5);
+    }
+
+    #[test]
+    fn inline_allow_unused_directives_are_honored() {
+        let fixture = ModFixture::new(&["$MyMod_Title"]).translation(
+            "MyMod_german.txt",
+            &[
+                "$MyMod_Title\tTitel",
+                "; mcm-meta-helper: allow-unused",
+                "$MyMod_Later\tSpäter",
+            ],
+        );
+        let stats = gather_stats(&mut fixture.moddir()).unwrap();
+        assert_eq!(stats["german"].unused, 0);
+    }
+}
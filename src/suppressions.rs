@@ -0,0 +1,292 @@
+//! Suppressions for problems the modder knows about and has decided to keep.
+//! These come from three places: `allowed_unused` in the project config, the
+//! suppressions file in the mod root, and directive comments inside the
+//! translation files themselves.
+//!
+//! The suppressions file has one entry per line: a problem kind, an optional
+//! language, and a key. Lines starting with `#` are comments.
+//!
+//! ```text
+//! unused $MyMod_FutureFeature
+//! missing german $MyMod_Dynamic
+//! ```
+//!
+//! A translation file can suppress problems with a comment line:
+//!
+//! ```text
+//! ; mcm-meta-helper: allow-unused
+//! $MyMod_FutureFeature    Something for later
+//! ; mcm-meta-helper: allow-missing $MyMod_Dynamic $MyMod_Other
+//! ```
+//!
+//! A directive with no keys applies to the entry on the following line.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::Path;
+
+use eyre::{Context, Result};
+
+/// The name of the suppressions file we look for in the mod root.
+pub const SUPPRESSIONS_FILE_NAME: &str = "mcm-meta-helper.suppress";
+
+/// The marker that starts a directive comment in a translation file.
+const DIRECTIVE: &str = "mcm-meta-helper:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProblemKind {
+    Missing,
+    Unused,
+}
+
+impl ProblemKind {
+    fn parse(input: &str) -> Option<Self> {
+        match input {
+            "missing" | "allow-missing" => Some(Self::Missing),
+            "unused" | "allow-unused" => Some(Self::Unused),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ProblemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemKind::Missing => write!(f, "missing"),
+            ProblemKind::Unused => write!(f, "unused"),
+        }
+    }
+}
+
+/// A single suppressed problem. A suppression without a language applies to
+/// every language.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Suppression {
+    pub kind: ProblemKind,
+    pub language: Option<String>,
+    pub key: String,
+}
+
+impl Display for Suppression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref language) = self.language {
+            write!(f, "{} {} {}", self.kind, language, self.key)
+        } else {
+            write!(f, "{} {}", self.kind, self.key)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+    entries: HashSet<Suppression>,
+}
+
+impl Suppressions {
+    /// Read a suppressions file. A file that doesn't exist suppresses nothing.
+    pub fn load(fpath: &Path) -> Result<Self> {
+        if !fpath.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(fpath)
+            .context(format!("reading the suppressions file {}", fpath.display()))?;
+
+        let mut entries = HashSet::new();
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pieces: Vec<&str> = line.split_whitespace().collect();
+            let kind = ProblemKind::parse(pieces[0]);
+            let entry = match (kind, pieces.len()) {
+                (Some(kind), 2) => Suppression {
+                    kind,
+                    language: None,
+                    key: pieces[1].to_string(),
+                },
+                (Some(kind), 3) => Suppression {
+                    kind,
+                    language: Some(pieces[1].to_lowercase()),
+                    key: pieces[2].to_string(),
+                },
+                _ => {
+                    log::warn!(
+                        "{}:{}: ignoring a suppression line I can't understand: {line}",
+                        fpath.display(),
+                        idx + 1
+                    );
+                    continue;
+                }
+            };
+            entries.insert(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Write these suppressions out, sorted, replacing the file's previous contents.
+    pub fn write(&self, fpath: &Path) -> Result<()> {
+        let mut entries: Vec<&Suppression> = self.entries.iter().collect();
+        entries.sort();
+        let mut output = String::from(
+            "# Problems mcm-meta-helper should not report. One per line:\n# <missing|unused> [language] <key>\n",
+        );
+        for entry in entries {
+            output.push_str(entry.to_string().as_str());
+            output.push('\n');
+        }
+        std::fs::write(fpath, output)
+            .context(format!("writing the suppressions file {}", fpath.display()))?;
+        Ok(())
+    }
+
+    pub fn extend(&mut self, other: impl IntoIterator<Item = Suppression>) {
+        self.entries.extend(other);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// True if this problem has been suppressed for the given language or for all languages.
+    pub fn allows(&self, kind: ProblemKind, language: &str, key: &str) -> bool {
+        self.entries.iter().any(|xs| {
+            xs.kind == kind
+                && xs.key == key
+                && xs
+                    .language
+                    .as_ref()
+                    .map(|lang| lang == language)
+                    .unwrap_or(true)
+        })
+    }
+}
+
+/// If this line is a directive comment, return the text after the marker.
+pub fn directive_text(line: &str) -> Option<&str> {
+    line.trim_start_matches([';', '/', '#', ' '])
+        .strip_prefix(DIRECTIVE)
+}
+
+/// Find the suppression directives in the lines of a translation file.
+pub fn inline_suppressions(language: &str, lines: &[(String, String)]) -> Vec<Suppression> {
+    let mut found = Vec::new();
+    let mut pending: Option<ProblemKind> = None;
+
+    for (key, value) in lines {
+        if !value.is_empty() {
+            if let Some(kind) = pending.take() {
+                found.push(Suppression {
+                    kind,
+                    language: Some(language.to_string()),
+                    key: key.trim().to_string(),
+                });
+            }
+            continue;
+        }
+
+        let Some(directive) = directive_text(key) else {
+            continue;
+        };
+        let mut words = directive.split_whitespace();
+        let Some(kind) = words.next().and_then(ProblemKind::parse) else {
+            continue;
+        };
+        let keys: Vec<&str> = words.collect();
+        if keys.is_empty() {
+            pending = Some(kind);
+        } else {
+            found.extend(keys.iter().map(|k| Suppression {
+                kind,
+                language: Some(language.to_string()),
+                key: k.to_string(),
+            }));
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<(String, String)> {
+        text.iter()
+            .map(|line| match line.split_once('\t') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (line.to_string(), String::new()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loads_and_writes_the_suppressions_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = dir.path().join(SUPPRESSIONS_FILE_NAME);
+        std::fs::write(
+            &fpath,
+            "# comment\n\nunused $MyMod_Future\nmissing German $MyMod_Dynamic\nnonsense here\n",
+        )
+        .unwrap();
+
+        let suppressions = Suppressions::load(&fpath).unwrap();
+        assert_eq!(suppressions.len(), 2);
+        assert!(suppressions.allows(ProblemKind::Unused, "french", "$MyMod_Future"));
+        assert!(suppressions.allows(ProblemKind::Missing, "german", "$MyMod_Dynamic"));
+        assert!(!suppressions.allows(ProblemKind::Missing, "french", "$MyMod_Dynamic"));
+        assert!(!suppressions.allows(ProblemKind::Missing, "german", "$MyMod_Future"));
+
+        suppressions.write(&fpath).unwrap();
+        let reloaded = Suppressions::load(&fpath).unwrap();
+        assert_eq!(reloaded.entries, suppressions.entries);
+    }
+
+    #[test]
+    fn a_missing_file_suppresses_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let suppressions = Suppressions::load(&dir.path().join("nothing-here")).unwrap();
+        assert!(suppressions.is_empty());
+    }
+
+    #[test]
+    fn directive_comments() {
+        assert_eq!(
+            directive_text("; mcm-meta-helper: allow-unused"),
+            Some(" allow-unused")
+        );
+        assert_eq!(
+            directive_text("// mcm-meta-helper: unused $A"),
+            Some(" unused $A")
+        );
+        assert_eq!(directive_text("; just a comment"), None);
+    }
+
+    #[test]
+    fn inline_directives_name_keys_or_apply_to_the_next_entry() {
+        let found = inline_suppressions(
+            "german",
+            &lines(&[
+                "; mcm-meta-helper: allow-missing $MyMod_A $MyMod_B",
+                "$MyMod_C\tC",
+                "; mcm-meta-helper: allow-unused",
+                "",
+                "$MyMod_D\tD",
+                "; mcm-meta-helper: allow-everything $MyMod_E",
+            ]),
+        );
+        let described: Vec<String> = found.iter().map(|xs| xs.to_string()).collect();
+        assert_eq!(
+            described,
+            [
+                "missing german $MyMod_A",
+                "missing german $MyMod_B",
+                "unused german $MyMod_D",
+            ]
+        );
+    }
+}
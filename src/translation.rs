@@ -3,7 +3,6 @@
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io::{BufRead, Read, Write};
 use std::path::PathBuf;
 
@@ -11,6 +10,16 @@ use byte_slice_cast::AsByteSlice;
 use byte_slice_cast::AsMutSliceOf;
 use eyre::{Context, Report, Result};
 
+/// The placeholder value `update` writes for a translation nobody has provided yet.
+pub fn stub_value(key: &str) -> String {
+    format!("translation for {}", key.replacen('$', "", 1))
+}
+
+/// True if this value is still the placeholder `update` wrote for the key.
+pub fn is_stub(key: &str, value: &str) -> bool {
+    value == stub_value(key)
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Translation {
     fpath: PathBuf,
@@ -18,6 +27,96 @@ pub struct Translation {
     language: String,
     translations: Option<HashMap<String, String>>,
     lines: Vec<(String, String)>,
+    /// Every line index where each key appears, in file order.
+    occurrences: HashMap<String, Vec<usize>>,
+    /// Whether the file starts with a byte order mark. New files get one.
+    bom: bool,
+    /// Whether the last line ends with a line ending. New files end with one.
+    final_newline: bool,
+}
+
+/// A key that appears more than once in a translation file.
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub key: String,
+    /// The line number and value of each appearance, in file order.
+    pub occurrences: Vec<(usize, String)>,
+}
+
+impl Duplicate {
+    /// True if the appearances don't all have the same value.
+    pub fn values_differ(&self) -> bool {
+        self.occurrences
+            .iter()
+            .any(|(_line, value)| *value != self.occurrences[0].1)
+    }
+}
+
+impl std::fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .occurrences
+            .iter()
+            .map(|(line, _value)| line.to_string())
+            .collect();
+        write!(f, "{} on lines {}", self.key, lines.join(", "))?;
+        if self.values_differ() {
+            let values: Vec<String> = self
+                .occurrences
+                .iter()
+                .map(|(line, value)| format!("    {line}: {value}"))
+                .collect();
+            write!(f, " with different values:\n{}", values.join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A line in a translation file that doesn't parse the way its author probably meant.
+#[derive(Debug, Clone)]
+pub struct MalformedLine {
+    /// The line number, counting from 1.
+    pub line: usize,
+    /// What's wrong with it.
+    pub problem: String,
+    /// The line as it probably should be written, if we can tell.
+    pub suggestion: Option<String>,
+    /// True if this hides a translation from the game, rather than being a stray line it ignores.
+    pub hides_translation: bool,
+}
+
+impl std::fmt::Display for MalformedLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.problem)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, "\n    try: {}", suggestion.replace('\t', "<TAB>"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Lines starting with these are comments, and the game skips them.
+const COMMENT_MARKERS: [char; 4] = [';', '#', '/', '-'];
+
+/// Split a line that separates its key from its value with spaces instead of a tab.
+fn split_on_spaces(line: &str) -> Option<(String, String)> {
+    if !line.starts_with('$') {
+        return None;
+    }
+    let (key, value) = line.split_once(char::is_whitespace)?;
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some((key.to_string(), value.to_string()))
+    }
+}
+
+/// Which appearance of a duplicated key to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeepOccurrence {
+    First,
+    Last,
 }
 
 impl Translation {
@@ -35,9 +134,20 @@ impl Translation {
             language,
             translations: None,
             lines: Vec::new(),
+            occurrences: HashMap::new(),
+            bom: true,
+            final_newline: true,
         }
     }
 
+    pub fn path(&self) -> &PathBuf {
+        &self.fpath
+    }
+
+    pub fn language(&self) -> &str {
+        self.language.as_str()
+    }
+
     pub fn display(&self) -> &str {
         self.display_name.as_str()
     }
@@ -46,6 +156,214 @@ impl Translation {
         &self.lines
     }
 
+    /// Whether the file ends with a line ending after its last line.
+    pub fn final_newline(&self) -> bool {
+        self.final_newline
+    }
+
+    pub fn set_final_newline(&mut self, final_newline: bool) {
+        self.final_newline = final_newline;
+    }
+
+    /// Replace every line of this file, e.g. to seed a new file from another language.
+    pub fn set_lines(&mut self, lines: Vec<(String, String)>) {
+        self.lines = lines;
+        self.index_lines();
+    }
+
+    /// Rebuild the key to value mapping and the record of where each key
+    /// appears from the file's lines. When a key appears more than once, the
+    /// mapping holds the last value.
+    fn index_lines(&mut self) {
+        let mut translations: HashMap<String, String> = HashMap::new();
+        let mut occurrences: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, (key, value)) in self.lines.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            let key = key.trim().to_owned();
+            translations.insert(key.clone(), value.trim().to_owned());
+            occurrences.entry(key).or_default().push(idx);
+        }
+        self.occurrences = occurrences;
+        self.set_translations(translations);
+    }
+
+    /// The line number where a key first appears, if it appears at all.
+    pub fn line_for(&self, key: &str) -> Option<usize> {
+        self.occurrences
+            .get(key)
+            .and_then(|indexes| indexes.first())
+            .map(|idx| idx + 1)
+    }
+
+    /// Every key that appears more than once, sorted by key.
+    pub fn duplicates(&mut self) -> Result<Vec<Duplicate>> {
+        if self.translations.is_none() {
+            self.load_translations()?;
+        }
+        let mut found: Vec<Duplicate> = self
+            .occurrences
+            .iter()
+            .filter(|(_key, indexes)| indexes.len() > 1)
+            .map(|(key, indexes)| Duplicate {
+                key: key.clone(),
+                occurrences: indexes
+                    .iter()
+                    .map(|idx| (idx + 1, self.lines[*idx].1.trim().to_string()))
+                    .collect(),
+            })
+            .collect();
+        found.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(found)
+    }
+
+    /// Find every line that looks like it was meant to be a translation but won't
+    /// be read as one, or that is neither a translation nor a comment.
+    pub fn malformed_lines(&mut self) -> Result<Vec<MalformedLine>> {
+        if self.translations.is_none() {
+            self.load_translations()?;
+        }
+
+        let mut found = Vec::new();
+        for (idx, (key, value)) in self.lines.iter().enumerate() {
+            let line = idx + 1;
+            if value.is_empty() {
+                if key.is_empty() || key.starts_with(COMMENT_MARKERS) {
+                    continue;
+                }
+                if let Some((k, v)) = split_on_spaces(key) {
+                    found.push(MalformedLine {
+                        line,
+                        problem: "key and value are separated by spaces instead of a tab"
+                            .to_string(),
+                        suggestion: Some(format!("{k}\t{v}")),
+                        hides_translation: true,
+                    });
+                } else if key.starts_with('$') {
+                    found.push(MalformedLine {
+                        line,
+                        problem: format!("key `{key}` has no value"),
+                        suggestion: None,
+                        hides_translation: true,
+                    });
+                } else {
+                    found.push(MalformedLine {
+                        line,
+                        problem: "not a translation or a comment".to_string(),
+                        suggestion: Some(format!("; {key}")),
+                        hides_translation: false,
+                    });
+                }
+                continue;
+            }
+
+            if key.trim() != key {
+                found.push(MalformedLine {
+                    line,
+                    problem: format!("key `{key}` has trailing whitespace"),
+                    suggestion: Some(format!("{}\t{value}", key.trim())),
+                    hides_translation: true,
+                });
+            }
+            if !key.trim().starts_with('$') {
+                found.push(MalformedLine {
+                    line,
+                    problem: format!("key `{}` does not start with $", key.trim()),
+                    suggestion: Some(format!("${}\t{value}", key.trim())),
+                    hides_translation: true,
+                });
+            } else if key.trim().contains(char::is_whitespace) {
+                found.push(MalformedLine {
+                    line,
+                    problem: format!("key `{}` contains whitespace", key.trim()),
+                    suggestion: None,
+                    hides_translation: true,
+                });
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Repair the malformed lines we can be sure about: entries separated by spaces
+    /// instead of a tab, and keys with trailing whitespace. Returns the number of
+    /// lines changed.
+    pub fn fix_malformed_lines(&mut self) -> Result<usize> {
+        if self.translations.is_none() {
+            self.load_translations()?;
+        }
+
+        let mut fixed = 0;
+        for (key, value) in self.lines.iter_mut() {
+            if value.is_empty() {
+                if let Some((k, v)) = split_on_spaces(key) {
+                    *key = k;
+                    *value = v;
+                    fixed += 1;
+                }
+            } else if key.trim() != key {
+                *key = key.trim().to_string();
+                fixed += 1;
+            }
+        }
+        if fixed > 0 {
+            self.index_lines();
+        }
+        Ok(fixed)
+    }
+
+    /// Remove every appearance of the given keys. Returns the number of lines removed.
+    pub fn remove_keys(&mut self, keys: &[String]) -> Result<usize> {
+        if self.translations.is_none() {
+            self.load_translations()?;
+        }
+        let before = self.lines.len();
+        self.lines
+            .retain(|(key, value)| value.is_empty() || !keys.contains(&key.trim().to_string()));
+        let removed = before - self.lines.len();
+        if removed > 0 {
+            self.index_lines();
+        }
+        Ok(removed)
+    }
+
+    /// Copy the file as it is on disk to a backup next to it, named with a `.bak`
+    /// extension added. Returns the backup's path.
+    pub fn backup(&self) -> Result<PathBuf> {
+        let mut name = self.fpath.clone().into_os_string();
+        name.push(".bak");
+        let backup = PathBuf::from(name);
+        std::fs::copy(&self.fpath, &backup).context(format!(
+            "backing up the {} translation file: {}",
+            self.language, self.display_name
+        ))?;
+        Ok(backup)
+    }
+
+    /// Remove all but one appearance of every duplicated key. Returns the number
+    /// of lines removed.
+    pub fn dedupe(&mut self, keep: KeepOccurrence) -> Result<usize> {
+        if self.translations.is_none() {
+            self.load_translations()?;
+        }
+        let mut doomed: Vec<usize> = self
+            .occurrences
+            .values()
+            .filter(|indexes| indexes.len() > 1)
+            .flat_map(|indexes| match keep {
+                KeepOccurrence::First => indexes[1..].to_vec(),
+                KeepOccurrence::Last => indexes[..indexes.len() - 1].to_vec(),
+            })
+            .collect();
+        doomed.sort();
+        for idx in doomed.iter().rev() {
+            self.lines.remove(*idx);
+        }
+        self.index_lines();
+        Ok(doomed.len())
+    }
+
     pub fn provided_translations(&mut self) -> Result<Vec<String>> {
         let map = self.translations()?;
         Ok(map.keys().cloned().collect())
@@ -82,8 +400,10 @@ impl Translation {
             "decoding the {} translation file: {}",
             self.language, self.display_name
         ))?;
-        let mut utf8bytes: Vec<u8> = vec![0; count];
-        let _widecount = match ucs2::decode(widebytes, &mut utf8bytes) {
+        self.bom = widebytes.first() == Some(&0xfeff);
+        // Each UCS-2 character needs at most three bytes in utf-8.
+        let mut utf8bytes: Vec<u8> = vec![0; count / 2 * 3];
+        let decoded = match ucs2::decode(widebytes, &mut utf8bytes) {
             Ok(c) => c,
             Err(e) => {
                 log::error!("{e:?}");
@@ -98,15 +418,16 @@ impl Translation {
                 };
             }
         };
+        utf8bytes.truncate(decoded);
+        self.final_newline = utf8bytes.ends_with(b"\n");
 
         let reader = std::io::BufReader::new(utf8bytes.as_slice()).lines();
         let mut lines: Vec<(String, String)> = Vec::new();
-        let mut translations: HashMap<String, String> = HashMap::new();
         for maybe_line in reader {
             let Ok(line) = maybe_line else {
                 continue;
             };
-            let line = line.trim().trim_matches('\0');
+            let line = line.trim().trim_start_matches('\u{feff}');
             if line.len() < 4 {
                 lines.push((line.to_string(), String::new()));
                 continue;
@@ -115,11 +436,10 @@ impl Translation {
                 lines.push((line.to_string(), String::new()));
                 continue;
             };
-            translations.insert(key.trim().to_owned(), value.trim().to_owned());
             lines.push((key.to_string(), value.to_string()));
         }
         self.lines = lines;
-        self.set_translations(translations.clone());
+        self.index_lines();
         Ok(())
     }
 
@@ -129,53 +449,84 @@ impl Translation {
         } else {
             self.lines.insert(idx, (key, value));
         }
+        self.index_lines();
+    }
+
+    /// Change the value of every line with this key. Returns false if the file
+    /// doesn't have the key.
+    pub fn set_value(&mut self, key: &str, value: &str) -> bool {
+        let Some(indexes) = self.occurrences.get(key) else {
+            return false;
+        };
+        for idx in indexes.clone() {
+            self.lines[idx].1 = value.to_string();
+        }
+        self.index_lines();
+        true
     }
 
     pub fn append_translation(&mut self, key: String, value: String) {
         self.lines.push((key, value));
+        self.index_lines();
     }
 
-    pub fn add_stub_translation(&mut self, stubs: &[&String]) -> Result<()> {
-        let mut lines: Vec<String> = Vec::new();
-        lines.push("\r\n---------- new translation stubs ----------\r\n".to_string());
-        stubs.iter().for_each(|stub| {
-            lines.push(format!(
-                "{}\ttranslation for {}",
-                stub,
-                stub.replacen('$', "", 1)
-            ));
-        });
-        lines.push("".to_string());
-
-        let input = lines.join("\r\n");
-        let mut widebuf: Vec<u16> = vec![0; input.len() * 2];
-        let count = match ucs2::encode(input.as_str(), &mut widebuf) {
-            Ok(v) => v,
-            Err(e) => return Err(Report::msg(format!("error while encoding strings: {e:?}"))),
-        };
-        widebuf.resize(count, 0);
+    /// The keys of this file's translations, in the order they appear.
+    pub fn ordered_keys(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter(|(_k, v)| !v.is_empty())
+            .map(|(k, _v)| k.trim().to_string())
+            .collect()
+    }
 
-        let narrow = widebuf.as_byte_slice();
-        let mut options = OpenOptions::new();
-        let mut file = options.append(true).open(&self.fpath)?;
-        file.write_all(narrow)?;
-        file.flush()?;
-        Ok(())
+    /// Insert a translation next to its neighbours in the first reference ordering
+    /// that mentions its key: right after the nearest earlier key this file has, or
+    /// right before the nearest later one. If no ordering helps, it goes at the end.
+    pub fn insert_in_order(&mut self, key: String, value: String, orders: &[&[String]]) {
+        for order in orders {
+            let Some(pos) = order.iter().position(|xs| *xs == key) else {
+                continue;
+            };
+            let before = order[..pos]
+                .iter()
+                .rev()
+                .find_map(|xs| self.occurrences.get(xs).and_then(|idx| idx.last()));
+            if let Some(idx) = before {
+                self.insert_at(key, value, idx + 1);
+                return;
+            }
+            let after = order[pos + 1..]
+                .iter()
+                .find_map(|xs| self.occurrences.get(xs).and_then(|idx| idx.first()));
+            if let Some(idx) = after {
+                self.insert_at(key, value, *idx);
+                return;
+            }
+        }
+        self.append_translation(key, value);
     }
 
+    /// Write this file's lines back out, replacing its previous contents.
+    /// Lines that aren't translations, like comments, are written unchanged.
     pub fn write(&self) -> Result<()> {
-        let input = self
+        let mut input = self
             .lines
             .iter()
             .map(|(k, v)| {
                 if v.is_empty() {
-                    "".to_string()
+                    k.to_string()
                 } else {
                     format!("{k}\t{v}")
                 }
             })
             .collect::<Vec<String>>()
             .join("\r\n");
+        if self.final_newline && !self.lines.is_empty() {
+            input.push_str("\r\n");
+        }
+        if self.bom {
+            input.insert(0, '\u{feff}');
+        }
         let mut widebuf: Vec<u16> = vec![0; input.len() * 2];
         let count = match ucs2::encode(input.as_str(), &mut widebuf) {
             Ok(v) => v,
@@ -184,10 +535,262 @@ impl Translation {
         widebuf.resize(count, 0);
 
         let narrow = widebuf.as_byte_slice();
-        let mut options = OpenOptions::new();
-        let mut file = options.append(true).open(&self.fpath)?;
+        let mut file = File::create(&self.fpath).context(format!(
+            "writing the {} translation file: {}",
+            self.language, self.display_name
+        ))?;
         file.write_all(narrow)?;
         file.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16_file(dir: &std::path::Path, text: &str) -> PathBuf {
+        let fpath = dir.join("MyMod_german.txt");
+        let bytes: Vec<u8> = text
+            .encode_utf16()
+            .flat_map(|xs| xs.to_le_bytes())
+            .collect();
+        std::fs::write(&fpath, bytes).unwrap();
+        fpath
+    }
+
+    #[test]
+    fn reads_exactly_the_lines_in_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = utf16_file(dir.path(), "\u{feff}$A\tÄpfel\r\n; note\r\n$B\tB\r\n");
+        let mut trfile = Translation::new(fpath, "german");
+        trfile.load_translations().unwrap();
+        assert_eq!(
+            trfile.lines(),
+            &[
+                ("$A".to_string(), "Äpfel".to_string()),
+                ("; note".to_string(), String::new()),
+                ("$B".to_string(), "B".to_string()),
+            ]
+        );
+        assert!(trfile.final_newline());
+    }
+
+    #[test]
+    fn keeps_the_final_line_ending_as_it_was() {
+        let dir = tempfile::tempdir().unwrap();
+        for text in ["\u{feff}$A\tA\r\n$B\tB\r\n", "\u{feff}$A\tA\r\n$B\tB"] {
+            let fpath = utf16_file(dir.path(), text);
+            let mut trfile = Translation::new(fpath.clone(), "german");
+            trfile.load_translations().unwrap();
+            assert_eq!(trfile.lines().len(), 2);
+            trfile.write().unwrap();
+
+            let written: Vec<u16> = std::fs::read(&fpath)
+                .unwrap()
+                .chunks(2)
+                .map(|xs| u16::from_le_bytes([xs[0], xs[1]]))
+                .collect();
+            assert_eq!(String::from_utf16(&written).unwrap(), text);
+        }
+    }
+
+    fn loaded(dir: &std::path::Path, text: &str) -> Translation {
+        let mut trfile = Translation::new(utf16_file(dir, text), "german");
+        trfile.load_translations().unwrap();
+        trfile
+    }
+
+    const DUPLICATED: &str = "$A\tfirst\r\n$B\tB\r\n$A\tsecond\r\n$B\tB\r\n$A\tthird\r\n";
+
+    #[test]
+    fn duplicates_report_every_line_a_key_appears_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut trfile = loaded(dir.path(), DUPLICATED);
+        let duplicates = trfile.duplicates().unwrap();
+        assert_eq!(duplicates.len(), 2);
+
+        assert_eq!(duplicates[0].key, "$A");
+        assert_eq!(
+            duplicates[0].occurrences,
+            [
+                (1, "first".to_string()),
+                (3, "second".to_string()),
+                (5, "third".to_string())
+            ]
+        );
+        assert!(duplicates[0].values_differ());
+        assert_eq!(
+            duplicates[0].to_string(),
+            "$A on lines 1, 3, 5 with different values:\n    1: first\n    3: second\n    5: third"
+        );
+
+        assert_eq!(duplicates[1].key, "$B");
+        assert!(!duplicates[1].values_differ());
+        assert_eq!(duplicates[1].to_string(), "$B on lines 2, 4");
+    }
+
+    #[test]
+    fn dedupe_keeps_the_first_or_last_appearance() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut trfile = loaded(dir.path(), DUPLICATED);
+        assert_eq!(trfile.dedupe(KeepOccurrence::First).unwrap(), 3);
+        assert_eq!(
+            trfile.lines(),
+            &[
+                ("$A".to_string(), "first".to_string()),
+                ("$B".to_string(), "B".to_string()),
+            ]
+        );
+        assert!(trfile.duplicates().unwrap().is_empty());
+
+        let mut trfile = loaded(dir.path(), DUPLICATED);
+        assert_eq!(trfile.dedupe(KeepOccurrence::Last).unwrap(), 3);
+        assert_eq!(
+            trfile.lines(),
+            &[
+                ("$B".to_string(), "B".to_string()),
+                ("$A".to_string(), "third".to_string()),
+            ]
+        );
+    }
+
+    const MALFORMED: &str = "\u{feff}; a comment\r\n# another\r\n$A  spaced   out\r\n$B\r\nstray text\r\n$C \tC\r\nKey\tD\r\n\r\n$E\tE\r\n";
+
+    #[test]
+    fn malformed_lines_explain_what_the_game_wont_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut trfile = loaded(dir.path(), MALFORMED);
+        let found: Vec<(usize, String, Option<String>, bool)> = trfile
+            .malformed_lines()
+            .unwrap()
+            .into_iter()
+            .map(|xs| (xs.line, xs.problem, xs.suggestion, xs.hides_translation))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    3,
+                    "key and value are separated by spaces instead of a tab".to_string(),
+                    Some("$A\tspaced   out".to_string()),
+                    true
+                ),
+                (4, "key `$B` has no value".to_string(), None, true),
+                (
+                    5,
+                    "not a translation or a comment".to_string(),
+                    Some("; stray text".to_string()),
+                    false
+                ),
+                (
+                    6,
+                    "key `$C ` has trailing whitespace".to_string(),
+                    Some("$C\tC".to_string()),
+                    true
+                ),
+                (
+                    7,
+                    "key `Key` does not start with $".to_string(),
+                    Some("$Key\tD".to_string()),
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixing_malformed_lines_changes_only_those_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut trfile = loaded(dir.path(), MALFORMED);
+        assert_eq!(trfile.fix_malformed_lines().unwrap(), 2);
+        trfile.write().unwrap();
+
+        let expected = MALFORMED
+            .replace("$A  spaced   out", "$A\tspaced   out")
+            .replace("$C \tC", "$C\tC");
+        let expected: Vec<u8> = expected
+            .encode_utf16()
+            .flat_map(|xs| xs.to_le_bytes())
+            .collect();
+        assert_eq!(std::fs::read(trfile.path()).unwrap(), expected);
+
+        let mut trfile = loaded(dir.path(), &decode_utf16(&expected));
+        let left: Vec<usize> = trfile
+            .malformed_lines()
+            .unwrap()
+            .iter()
+            .map(|xs| xs.line)
+            .collect();
+        assert_eq!(left, [4, 5, 7]);
+    }
+
+    fn decode_utf16(bytes: &[u8]) -> String {
+        let units: Vec<u16> = bytes
+            .chunks(2)
+            .map(|xs| u16::from_le_bytes([xs[0], xs[1]]))
+            .collect();
+        String::from_utf16(&units).unwrap()
+    }
+
+    fn keys(list: &[&str]) -> Vec<String> {
+        list.iter().map(|xs| xs.to_string()).collect()
+    }
+
+    fn with_keys(list: &[&str]) -> Translation {
+        let mut trfile = Translation::new(PathBuf::from("MyMod_german.txt"), "german");
+        trfile.set_lines(
+            list.iter()
+                .map(|line| match line.strip_prefix('$') {
+                    Some(name) => (line.to_string(), name.to_lowercase()),
+                    None => (line.to_string(), String::new()),
+                })
+                .collect(),
+        );
+        trfile
+    }
+
+    #[test]
+    fn insert_in_order_places_keys_next_to_their_neighbours() {
+        let order = keys(&["$A", "$B", "$C", "$D", "$E"]);
+        let mut trfile = with_keys(&["$B", "$D"]);
+        trfile.insert_in_order("$A".into(), "a".into(), &[order.as_slice()]);
+        trfile.insert_in_order("$C".into(), "c".into(), &[order.as_slice()]);
+        trfile.insert_in_order("$E".into(), "e".into(), &[order.as_slice()]);
+        assert_eq!(trfile.ordered_keys(), keys(&["$A", "$B", "$C", "$D", "$E"]));
+    }
+
+    #[test]
+    fn insert_in_order_goes_after_the_nearest_earlier_key() {
+        // The file's own order wins over the reference order between its keys.
+        let order = keys(&["$A", "$B", "$C", "$D"]);
+        let mut trfile = with_keys(&["$D", "; a comment", "$A"]);
+        trfile.insert_in_order("$B".into(), "b".into(), &[order.as_slice()]);
+        assert_eq!(trfile.ordered_keys(), keys(&["$D", "$A", "$B"]));
+        trfile.insert_in_order("$C".into(), "c".into(), &[order.as_slice()]);
+        assert_eq!(trfile.ordered_keys(), keys(&["$D", "$A", "$B", "$C"]));
+    }
+
+    #[test]
+    fn insert_in_order_falls_back_to_later_orderings_and_the_end() {
+        let source = keys(&["$A", "$B"]);
+        let config = keys(&["$B", "$X", "$C"]);
+        let mut trfile = with_keys(&["$B", "$C"]);
+        trfile.insert_in_order(
+            "$X".into(),
+            "x".into(),
+            &[source.as_slice(), config.as_slice()],
+        );
+        assert_eq!(trfile.ordered_keys(), keys(&["$B", "$X", "$C"]));
+        trfile.insert_in_order(
+            "$Z".into(),
+            "z".into(),
+            &[source.as_slice(), config.as_slice()],
+        );
+        assert_eq!(trfile.ordered_keys(), keys(&["$B", "$X", "$C", "$Z"]));
+
+        let mut empty = with_keys(&[]);
+        empty.insert_in_order("$A".into(), "a".into(), &[source.as_slice()]);
+        assert_eq!(empty.ordered_keys(), keys(&["$A"]));
+    }
+}
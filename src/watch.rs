@@ -0,0 +1,184 @@
+//! Re-run a command whenever the mod's files change, for keeping a report on
+//! screen while editing config.json and translations.
+
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+use eyre::{Report, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+
+use crate::{Args, ModDirectory, CONFIG_FILE_NAME};
+
+/// How long to wait for more changes before re-running, so that an editor
+/// saving several files at once triggers one run instead of several.
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Run once, then again every time a file in the data directory or the project
+/// config changes. Files that didn't change are not parsed again. Runs until
+/// interrupted.
+pub fn watch<F>(args: &Args, mut run: F) -> Result<bool, Report>
+where
+    F: FnMut(&mut ModDirectory) -> Result<bool, Report>,
+{
+    let mut moddir = ModDirectory::new(args.moddir.as_str())?;
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(moddir.datadir(), RecursiveMode::Recursive)?;
+    if moddir.datadir() != moddir.modpath() {
+        watcher.watch(moddir.modpath(), RecursiveMode::NonRecursive)?;
+    }
+
+    rerun(&mut run, &mut moddir, &[]);
+    loop {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        let event = receiver.recv()?;
+        collect_changes(&moddir, event, &mut changed);
+        loop {
+            match receiver.recv_timeout(SETTLE_TIME) {
+                Ok(event) => collect_changes(&moddir, event, &mut changed),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(true),
+            }
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        // A changed project config or suppressions file changes everything.
+        let settings_changed = changed
+            .iter()
+            .any(|xs| classify_change(&moddir, xs) == Change::Settings);
+        if settings_changed {
+            match ModDirectory::new(args.moddir.as_str()) {
+                Ok(fresh) => moddir = fresh,
+                Err(e) => {
+                    log::error!("{e:#}");
+                    continue;
+                }
+            }
+        } else {
+            moddir.forget(&changed);
+        }
+        rerun(&mut run, &mut moddir, &changed);
+    }
+}
+
+/// Add the files an event touched to the list of changes, skipping anything we ignore.
+fn collect_changes(
+    moddir: &ModDirectory,
+    event: notify::Result<notify::Event>,
+    changed: &mut Vec<PathBuf>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            log::warn!("Trouble watching for changes: {e}");
+            return;
+        }
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    for path in event.paths {
+        if classify_change(moddir, &path) == Change::Ignored || changed.contains(&path) {
+            continue;
+        }
+        changed.push(path);
+    }
+}
+
+/// What a changed file means for the next run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    /// A file we never look at; it doesn't trigger a run.
+    Ignored,
+    /// The project config or the suppressions file, which change every result.
+    Settings,
+    /// A json, translation, or source file, which only needs to be read again.
+    File,
+}
+
+/// Decide whether a changed file should trigger a run, and whether it changes
+/// the settings or just one file's contents.
+fn classify_change(moddir: &ModDirectory, path: &Path) -> Change {
+    if path
+        .file_name()
+        .map(|name| name == CONFIG_FILE_NAME)
+        .unwrap_or(false)
+        || path == moddir.suppressions_path()
+    {
+        Change::Settings
+    } else if moddir.is_ignored(path) {
+        Change::Ignored
+    } else {
+        Change::File
+    }
+}
+
+fn rerun<F>(run: &mut F, moddir: &mut ModDirectory, changed: &[PathBuf])
+where
+    F: FnMut(&mut ModDirectory) -> Result<bool, Report>,
+{
+    if std::io::stdout().is_terminal() {
+        // Clear the screen so the new report replaces the old one.
+        print!("\x1b[2J\x1b[H");
+    }
+    if !changed.is_empty() {
+        let names: Vec<String> = changed
+            .iter()
+            .map(|xs| moddir.relative_display(xs))
+            .collect();
+        log::info!("Changed: {}", names.join(", ").blue());
+    }
+    match run(moddir) {
+        Ok(true) => log::info!("{}", "All checks passed.".green()),
+        Ok(false) => log::warn!("{}", "The checks found problems you need to fix.".red()),
+        Err(e) => log::error!("{e:#}"),
+    }
+    log::info!("Watching for changes; press ctrl-c to stop.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ModFixture;
+
+    #[test]
+    fn classify_change_sorts_settings_from_files_and_skips_ignored_paths() {
+        let fixture = ModFixture::new(&["$MyMod_Title"]).file(
+            "mcm-meta-helper.toml",
+            "ignore = [\"data/Scripts/Source/old\"]\n",
+        );
+        let moddir = fixture.moddir();
+        let top = moddir.modpath().to_path_buf();
+
+        for settings in [top.join(CONFIG_FILE_NAME), moddir.suppressions_path()] {
+            assert_eq!(classify_change(&moddir, &settings), Change::Settings);
+        }
+        for file in [
+            "data/mcm/config/MyMod/config.json",
+            "data/Interface/Translations/MyMod_english.txt",
+            "data/Scripts/Source/MyMod.psc",
+        ] {
+            assert_eq!(
+                classify_change(&moddir, &top.join(file)),
+                Change::File,
+                "{file}"
+            );
+        }
+        for ignored in [
+            "data/Scripts/Source/old/MyMod.psc",
+            ".git/index",
+            "build/MyMod.zip",
+        ] {
+            assert_eq!(
+                classify_change(&moddir, &top.join(ignored)),
+                Change::Ignored,
+                "{ignored}"
+            );
+        }
+    }
+}
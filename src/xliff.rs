@@ -0,0 +1,410 @@
+//! XLIFF 2.0 files, for localization vendors. Each translation key is a unit
+//! named after the key, with one segment holding the source language text and
+//! the translation. A segment's state is `translated` when the language has a
+//! real translation, and `initial` when it's missing, a stub, or a copy of the
+//! source text.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result};
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::{language_code, language_for_code, ExchangeEntry, ExchangeSet, ImportedTranslations};
+
+const XLIFF_NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:2.0";
+
+/// Write an XLIFF file for every language other than the source language.
+pub fn write_xliff_files(set: &ExchangeSet, output: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for language in set.languages.iter() {
+        let fpath = output.join(format!("{}_{language}.xlf", set.name));
+        let xml = xliff_for(set, language).context(format!("writing {}", fpath.display()))?;
+        std::fs::write(&fpath, xml).context(format!("writing {}", fpath.display()))?;
+        written.push(fpath);
+    }
+    Ok(written)
+}
+
+fn xliff_for(set: &ExchangeSet, language: &str) -> Result<Vec<u8>> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    // Both languages must be BCP 47 codes, which Skyrim's language names aren't.
+    let code_for = |language: &str| {
+        language_code(language).ok_or_else(|| {
+            eyre::eyre!("There is no language code for {language}, which XLIFF files need.")
+        })
+    };
+    let source_code = code_for(&set.source_language)?;
+    let target_code = code_for(language)?;
+    writer
+        .create_element("xliff")
+        .with_attributes([
+            ("xmlns", XLIFF_NAMESPACE),
+            ("version", "2.0"),
+            ("srcLang", source_code),
+            ("trgLang", target_code),
+        ])
+        .write_inner_content(|writer| {
+            writer
+                .create_element("file")
+                .with_attributes([("id", "f1"), ("original", set.name.as_str())])
+                .write_inner_content(|writer| {
+                    for (idx, entry) in set.entries.iter().enumerate() {
+                        write_unit(writer, idx + 1, entry, language)?;
+                    }
+                    Ok::<(), quick_xml::Error>(())
+                })?;
+            Ok::<(), quick_xml::Error>(())
+        })?;
+    let mut xml = writer.into_inner();
+    xml.push(b'\n');
+    Ok(xml)
+}
+
+fn write_unit(
+    writer: &mut Writer<Vec<u8>>,
+    number: usize,
+    entry: &ExchangeEntry,
+    language: &str,
+) -> Result<(), quick_xml::Error> {
+    // Unit ids must be xml name tokens, which keys starting with $ aren't.
+    let id = format!("u{number}");
+    let translation = entry.translations.get(language);
+    let state = if translation.is_some() {
+        "translated"
+    } else {
+        "initial"
+    };
+    writer
+        .create_element("unit")
+        .with_attributes([("id", id.as_str()), ("name", entry.key.as_str())])
+        .write_inner_content(|writer| {
+            if !entry.locations.is_empty() {
+                writer
+                    .create_element("notes")
+                    .write_inner_content(|writer| {
+                        for location in entry.locations.iter() {
+                            writer
+                                .create_element("note")
+                                .with_attribute(("category", "location"))
+                                .write_text_content(BytesText::new(location))?;
+                        }
+                        Ok::<(), quick_xml::Error>(())
+                    })?;
+            }
+            writer
+                .create_element("segment")
+                .with_attribute(("state", state))
+                .write_inner_content(|writer| {
+                    let source = entry.source.as_deref().unwrap_or_default();
+                    writer
+                        .create_element("source")
+                        .write_text_content(BytesText::new(source))?;
+                    if let Some(translation) = translation {
+                        writer
+                            .create_element("target")
+                            .write_text_content(BytesText::new(translation))?;
+                    }
+                    Ok::<(), quick_xml::Error>(())
+                })?;
+            Ok::<(), quick_xml::Error>(())
+        })?;
+    Ok(())
+}
+
+/// Read the translations from an XLIFF 2.0 file, checking that it is one.
+/// Segments still in the `initial` state, including segments with no state,
+/// are skipped, since nobody has translated them yet.
+pub fn read_xliff(fpath: &Path, source_language: &str) -> Result<Vec<ImportedTranslations>> {
+    let text = std::fs::read_to_string(fpath).context(format!("reading {}", fpath.display()))?;
+    let mut reader = Reader::from_str(&text);
+    // Whitespace at the edges of a target is part of the translation, so keep it.
+    reader.trim_text(false);
+    let invalid = |reader: &Reader<&[u8]>, problem: String| {
+        let line = text[..reader.buffer_position().min(text.len())]
+            .matches('\n')
+            .count()
+            + 1;
+        eyre::eyre!(
+            "{} is not a valid XLIFF 2.0 file: {problem} (near line {line})",
+            fpath.display()
+        )
+    };
+
+    let mut language: Option<String> = None;
+    let mut values = Vec::new();
+    let mut key: Option<String> = None;
+    let mut state: Option<String> = None;
+    let mut target: Option<String> = None;
+    // A vendor's tool may split a unit into several segments.
+    let mut segments: Vec<String> = Vec::new();
+    let mut complete = true;
+    let mut in_target = false;
+    let mut depth = 0;
+    loop {
+        let event = reader
+            .read_event()
+            .context(format!("parsing {}", fpath.display()))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let name = element.local_name();
+                let name = String::from_utf8_lossy(name.as_ref()).to_string();
+                let attribute = |attr: &str| -> Result<Option<String>> {
+                    Ok(match element.try_get_attribute(attr)? {
+                        Some(value) => Some(value.unescape_value()?.to_string()),
+                        None => None,
+                    })
+                };
+                if in_target {
+                    return Err(invalid(
+                        &reader,
+                        format!("inline <{name}> markup in a target isn't supported"),
+                    ));
+                }
+                if depth == 0 {
+                    if name != "xliff" {
+                        return Err(invalid(
+                            &reader,
+                            format!("the root is <{name}>, not <xliff>"),
+                        ));
+                    }
+                    if attribute("version")?.as_deref() != Some("2.0") {
+                        return Err(invalid(&reader, "the version isn't 2.0".to_string()));
+                    }
+                    let source = attribute("srcLang")?.unwrap_or_default();
+                    if language_for_code(&source).unwrap_or(source.clone()) != source_language {
+                        return Err(invalid(
+                            &reader,
+                            format!("the source language is '{source}', not {source_language}"),
+                        ));
+                    }
+                    let Some(code) = attribute("trgLang")? else {
+                        return Err(invalid(&reader, "there is no trgLang".to_string()));
+                    };
+                    language = Some(language_for_code(&code).unwrap_or(code));
+                }
+                match name.as_str() {
+                    "unit" => {
+                        let Some(name) = attribute("name")? else {
+                            return Err(invalid(
+                                &reader,
+                                "a unit has no name to give its translation key".to_string(),
+                            ));
+                        };
+                        key = Some(name);
+                    }
+                    "segment" => {
+                        state = attribute("state")?;
+                        target = None;
+                    }
+                    "ignorable" => target = None,
+                    "target" => {
+                        in_target = matches!(event, Event::Start(_));
+                        target = Some(String::new());
+                    }
+                    _ => {}
+                }
+                if matches!(event, Event::Start(_)) {
+                    depth += 1;
+                }
+            }
+            Event::Text(ref content) if in_target => {
+                let content = content.unescape()?;
+                target.get_or_insert_with(String::new).push_str(&content);
+            }
+            Event::CData(ref content) if in_target => {
+                let content = String::from_utf8_lossy(content.as_ref()).to_string();
+                target.get_or_insert_with(String::new).push_str(&content);
+            }
+            Event::End(ref element) => {
+                depth -= 1;
+                match element.local_name().as_ref() {
+                    b"target" => in_target = false,
+                    b"segment" => {
+                        if key.is_none() {
+                            return Err(invalid(
+                                &reader,
+                                "a segment is outside of any unit".to_string(),
+                            ));
+                        }
+                        // XLIFF 2.0 says a segment without a state is initial.
+                        let translated = state.as_deref().unwrap_or("initial") != "initial";
+                        match target.take() {
+                            Some(value) if translated && !value.trim().is_empty() => {
+                                segments.push(value);
+                            }
+                            _ => complete = false,
+                        }
+                    }
+                    // The text between segments, usually whitespace.
+                    b"ignorable" => segments.extend(target.take()),
+                    b"unit" => {
+                        if let Some(key) = key.take() {
+                            if complete && !segments.is_empty() {
+                                values.push((key, segments.concat().trim().to_string()));
+                            }
+                        }
+                        segments.clear();
+                        complete = true;
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let Some(language) = language else {
+        return Err(eyre::eyre!(
+            "{} is not a valid XLIFF 2.0 file: it is empty.",
+            fpath.display()
+        ));
+    };
+    Ok(vec![ImportedTranslations { language, values }])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn exchange_set() -> ExchangeSet {
+        let entry = |key: &str, source: &str, german: Option<&str>| ExchangeEntry {
+            key: key.to_string(),
+            locations: vec!["data/mcm/config/MyMod/config.json:3".to_string()],
+            source: Some(source.to_string()),
+            translations: german
+                .map(|xs| HashMap::from([("german".to_string(), xs.to_string())]))
+                .unwrap_or_default(),
+        };
+        ExchangeSet {
+            name: "MyMod".to_string(),
+            source_language: "english".to_string(),
+            languages: vec!["german".to_string()],
+            entries: vec![
+                entry("$MyMod_Title", "Title & more", Some("Titel & mehr")),
+                entry("$MyMod_Help", "Help", None),
+            ],
+        }
+    }
+
+    fn read(xml: &str) -> Result<Vec<ImportedTranslations>> {
+        let dir = tempfile::tempdir().unwrap();
+        let fpath = dir.path().join("MyMod_german.xlf");
+        std::fs::write(&fpath, xml).unwrap();
+        read_xliff(&fpath, "english")
+    }
+
+    fn document(units: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="en" trgLang="de-DE">
+  <file id="f1">{units}</file>
+</xliff>"#
+        )
+    }
+
+    #[test]
+    fn round_trips_translated_segments() {
+        let xml = xliff_for(&exchange_set(), "german").unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.contains(r#"trgLang="de""#));
+        assert!(xml.contains(r#"name="$MyMod_Help""#));
+
+        let imported = read(&xml).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].language, "german");
+        assert_eq!(
+            imported[0].values,
+            [("$MyMod_Title".to_string(), "Titel & mehr".to_string())]
+        );
+    }
+
+    #[test]
+    fn joins_a_units_segments() {
+        let imported = read(&document(
+            r#"<unit id="u1" name="$MyMod_Help">
+                 <segment state="translated"><source>One.</source><target>Eins.</target></segment>
+                 <ignorable><source> </source><target> </target></ignorable>
+                 <segment state="final"><source>Two.</source><target>Zwei.</target></segment>
+               </unit>
+               <unit id="u2" name="$MyMod_Half">
+                 <segment state="translated"><source>One.</source><target>Eins.</target></segment>
+                 <segment state="initial"><source>Two.</source></segment>
+               </unit>"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            imported[0].values,
+            [("$MyMod_Help".to_string(), "Eins. Zwei.".to_string())]
+        );
+    }
+
+    #[test]
+    fn joins_segments_without_adding_a_separator() {
+        let imported = read(&document(
+            r#"<unit id="u1" name="$MyMod_Help">
+                 <segment state="translated"><source>彼は</source><target>彼は</target></segment>
+                 <segment state="translated"><source>来た。</source><target>来た。</target></segment>
+               </unit>
+               <unit id="u2" name="$MyMod_Title">
+                 <segment state="translated"><source>One.</source><target>Eins. </target></segment>
+                 <segment state="translated"><source>Two.</source><target>Zwei.</target></segment>
+               </unit>"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            imported[0].values,
+            [
+                ("$MyMod_Help".to_string(), "彼は来た。".to_string()),
+                ("$MyMod_Title".to_string(), "Eins. Zwei.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn segments_without_a_state_are_initial() {
+        let imported = read(&document(
+            r#"<unit id="u1" name="$MyMod_Help">
+                 <segment><source>Help</source><target>Hilfe</target></segment>
+               </unit>"#,
+        ))
+        .unwrap();
+        assert!(imported[0].values.is_empty());
+    }
+
+    #[test]
+    fn rejects_inline_markup() {
+        let result = read(&document(
+            r#"<unit id="u1" name="$MyMod_Help">
+                 <segment state="translated"><source>Help</source><target>Hi<pc id="1">lfe</pc></target></segment>
+               </unit>"#,
+        ));
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("inline <pc> markup"), "{message}");
+    }
+
+    #[test]
+    fn rejects_a_different_source_language() {
+        let xml = document("").replace(r#"srcLang="en""#, r#"srcLang="fr""#);
+        let message = read(&xml).unwrap_err().to_string();
+        assert!(message.contains("the source language is 'fr'"), "{message}");
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let xml = document("").replace(r#"version="2.0""#, r#"version="1.2""#);
+        assert!(read(&xml).is_err());
+    }
+
+    #[test]
+    fn needs_a_language_code_to_export() {
+        let mut set = exchange_set();
+        set.languages = vec!["elvish".to_string()];
+        let message = xliff_for(&set, "elvish").unwrap_err().to_string();
+        assert!(message.contains("no language code for elvish"), "{message}");
+    }
+}